@@ -0,0 +1,318 @@
+use std::{collections::BTreeMap, fmt};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    components::{Climbing, InputHistory},
+    input,
+    physics::{Collider, CollidingSide, Velocity},
+};
+
+/// horizontal speed below which a grounded character counts as standing still
+const RUN_THRESHOLD: f32 = 0.1;
+
+/// animations only read rollback state and are never rolled back themselves, so they can't
+/// cause a desync
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationSet>()
+            .register_asset_loader(AnimationSetLoader)
+            .add_systems(
+                Update,
+                (attach_sprite_sheets, pick_animations, advance_animations).chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize)]
+pub enum AnimationKind {
+    #[default]
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Climb,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Clip {
+    /// indices into the sprite sheet, left to right then top to bottom
+    pub frames: Vec<usize>,
+    pub fps: f32,
+    /// clips that don't loop hold their last frame
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+impl Clip {
+    /// the frame after `frame`, wrapping around or holding the last one
+    fn next_frame(&self, frame: usize) -> usize {
+        let last = self.frames.len().saturating_sub(1);
+
+        match self.looping {
+            true => (frame + 1) % self.frames.len().max(1),
+            false => (frame + 1).min(last),
+        }
+    }
+}
+
+/// a character's sprite sheet and the clips in it, loaded from an `.anim.ron` file
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct AnimationSet {
+    pub sheet: String,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub clips: BTreeMap<AnimationKind, Clip>,
+}
+
+#[derive(Default)]
+struct AnimationSetLoader;
+
+#[derive(Debug)]
+pub enum AnimationLoadError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for AnimationLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationLoadError::Io(e) => write!(f, "failed to read animation set: {e}"),
+            AnimationLoadError::Ron(e) => write!(f, "failed to parse animation set: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AnimationLoadError {}
+
+impl From<std::io::Error> for AnimationLoadError {
+    fn from(e: std::io::Error) -> Self {
+        AnimationLoadError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for AnimationLoadError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        AnimationLoadError::Ron(e)
+    }
+}
+
+impl AssetLoader for AnimationSetLoader {
+    type Asset = AnimationSet;
+    type Settings = ();
+    type Error = AnimationLoadError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<AnimationSet, AnimationLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+/// what a character is showing right now, purely visual
+#[derive(Component, Debug)]
+pub struct CharacterAnimation {
+    set: Handle<AnimationSet>,
+    kind: AnimationKind,
+    /// index into the current clip's frames
+    frame: usize,
+    elapsed: f32,
+}
+
+impl CharacterAnimation {
+    pub fn new(set: Handle<AnimationSet>) -> Self {
+        Self {
+            set,
+            kind: AnimationKind::default(),
+            frame: 0,
+            elapsed: 0.,
+        }
+    }
+
+    fn play(&mut self, kind: AnimationKind) {
+        if self.kind != kind {
+            self.kind = kind;
+            self.frame = 0;
+            self.elapsed = 0.;
+        }
+    }
+}
+
+/// swaps the placeholder texture for the sprite sheet once the animation set has loaded
+fn attach_sprite_sheets(
+    mut commands: Commands,
+    characters: Query<(Entity, &CharacterAnimation), Without<TextureAtlas>>,
+    sets: Res<Assets<AnimationSet>>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, animation) in &characters {
+        let Some(set) = sets.get(&animation.set) else {
+            continue;
+        };
+
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::new(set.frame_width, set.frame_height),
+            set.columns,
+            set.rows,
+            None,
+            None,
+        );
+
+        commands.entity(entity).insert((
+            asset_server.load::<Image>(&set.sheet),
+            TextureAtlas {
+                layout: layouts.add(layout),
+                index: 0,
+            },
+        ));
+    }
+}
+
+fn pick_animations(
+    mut characters: Query<(
+        &mut CharacterAnimation,
+        &mut Sprite,
+        &Velocity,
+        &Collider,
+        &Climbing,
+        &InputHistory,
+    )>,
+) {
+    for (mut animation, mut sprite, velocity, collider, climbing, history) in &mut characters {
+        let grounded = collider.check_colliding_solid_side(CollidingSide::Bottom);
+
+        let kind = if climbing.0 {
+            AnimationKind::Climb
+        } else if grounded && velocity.0.x.abs() > RUN_THRESHOLD {
+            AnimationKind::Run
+        } else if grounded {
+            AnimationKind::Idle
+        } else if velocity.0.y > 0. {
+            AnimationKind::Jump
+        } else {
+            AnimationKind::Fall
+        };
+
+        animation.play(kind);
+
+        // face the way the player is pushing, keeping the last direction when they let go
+        let direction = input::direction(history.current());
+
+        if direction.x < 0. {
+            sprite.flip_x = true;
+        } else if direction.x > 0. {
+            sprite.flip_x = false;
+        }
+    }
+}
+
+fn advance_animations(
+    mut characters: Query<(&mut CharacterAnimation, &mut TextureAtlas)>,
+    sets: Res<Assets<AnimationSet>>,
+    time: Res<Time<Real>>,
+) {
+    for (mut animation, mut atlas) in &mut characters {
+        let Some(clip) = sets
+            .get(&animation.set)
+            .and_then(|set| set.clips.get(&animation.kind))
+        else {
+            continue;
+        };
+
+        if clip.frames.is_empty() {
+            continue;
+        }
+
+        animation.elapsed += time.delta_seconds();
+
+        let frame_time = 1. / clip.fps.max(f32::EPSILON);
+
+        while animation.elapsed >= frame_time {
+            animation.elapsed -= frame_time;
+
+            animation.frame = clip.next_frame(animation.frame);
+        }
+
+        let index = clip.frames[animation.frame.min(clip.frames.len() - 1)];
+
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(looping: bool) -> Clip {
+        Clip {
+            frames: vec![4, 5, 6],
+            fps: 10.,
+            looping,
+        }
+    }
+
+    #[test]
+    fn looping_clips_wrap_and_others_hold_their_last_frame() {
+        assert_eq!(clip(true).next_frame(1), 2);
+        assert_eq!(clip(true).next_frame(2), 0);
+        assert_eq!(clip(false).next_frame(2), 2);
+    }
+
+    #[test]
+    fn clips_loop_unless_told_not_to() {
+        let clip: Clip = ron::from_str("(frames: [0, 1], fps: 8.0)").unwrap();
+
+        assert!(clip.looping);
+    }
+
+    /// every shipped set parses and only points at frames its sheet has
+    #[test]
+    fn character_animation_sets_fit_their_sheets() {
+        for name in ["nat", "aiden"] {
+            let path = format!(
+                "{}/assets/animations/{name}.anim.ron",
+                env!("CARGO_MANIFEST_DIR")
+            );
+            let set: AnimationSet = ron::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            let frame_count = (set.columns * set.rows) as usize;
+
+            assert!(
+                set.clips.contains_key(&AnimationKind::Idle),
+                "{name} has no idle clip"
+            );
+
+            for (kind, clip) in &set.clips {
+                assert!(!clip.frames.is_empty(), "{name} {kind:?} has no frames");
+                assert!(
+                    clip.frames.iter().all(|frame| *frame < frame_count),
+                    "{name} {kind:?} points past the end of its sheet"
+                );
+            }
+        }
+    }
+}
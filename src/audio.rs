@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    ecs::system::SystemParam,
+    prelude::*,
+};
+
+use crate::{
+    rollback::{RollbackFrame, SimulatedFrames},
+    settings::Settings,
+    AppState, Persistent,
+};
+
+/// how many frames back a played sound is remembered, rollbacks never go further than this so
+/// a resimulated frame can't play its sounds twice or miss cancelling one
+const SOUND_MEMORY: u32 = 16;
+
+/// music for each part of the game and sound effects for gameplay and menus, volumes come from
+/// `Settings` with the master volume on top
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundAssets>()
+            .init_resource::<RollbackSounds>()
+            .add_event::<PlaySound>()
+            .add_systems(
+                Update,
+                (
+                    play_state_music.run_if(state_changed::<AppState>),
+                    apply_music_volume.run_if(resource_changed::<Settings>),
+                    play_rollback_sounds,
+                    play_sounds,
+                ),
+            )
+            .add_systems(OnExit(AppState::FullLobby), reset_rollback_sounds);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Sfx {
+    Jump,
+    Land,
+    Climb,
+    Door,
+    UiMove,
+    UiSelect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Menu,
+    Lobby,
+    Level,
+}
+
+impl Track {
+    fn for_state(state: &AppState) -> Self {
+        match state {
+            AppState::MainMenu
+            | AppState::SettingsMenu
+            | AppState::PastGamesMenu
+            | AppState::CreateGameMenu
+            | AppState::JoinGameMenu => Track::Menu,
+            AppState::WaitingInLobby | AppState::LocalLobby => Track::Lobby,
+            AppState::FullLobby => Track::Level,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+struct SoundAssets {
+    menu_music: Handle<AudioSource>,
+    lobby_music: Handle<AudioSource>,
+    level_music: Handle<AudioSource>,
+    jump: Handle<AudioSource>,
+    land: Handle<AudioSource>,
+    climb: Handle<AudioSource>,
+    door: Handle<AudioSource>,
+    ui_move: Handle<AudioSource>,
+    ui_select: Handle<AudioSource>,
+}
+
+impl FromWorld for SoundAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+
+        Self {
+            menu_music: assets.load("audio/music/menu.wav"),
+            lobby_music: assets.load("audio/music/lobby.wav"),
+            level_music: assets.load("audio/music/level.wav"),
+            jump: assets.load("audio/sfx/jump.wav"),
+            land: assets.load("audio/sfx/land.wav"),
+            climb: assets.load("audio/sfx/climb.wav"),
+            door: assets.load("audio/sfx/door.wav"),
+            ui_move: assets.load("audio/sfx/ui_move.wav"),
+            ui_select: assets.load("audio/sfx/ui_select.wav"),
+        }
+    }
+}
+
+impl SoundAssets {
+    fn music(&self, track: Track) -> Handle<AudioSource> {
+        match track {
+            Track::Menu => self.menu_music.clone(),
+            Track::Lobby => self.lobby_music.clone(),
+            Track::Level => self.level_music.clone(),
+        }
+    }
+
+    fn sfx(&self, sound: Sfx) -> Handle<AudioSource> {
+        match sound {
+            Sfx::Jump => self.jump.clone(),
+            Sfx::Land => self.land.clone(),
+            Sfx::Climb => self.climb.clone(),
+            Sfx::Door => self.door.clone(),
+            Sfx::UiMove => self.ui_move.clone(),
+            Sfx::UiSelect => self.ui_select.clone(),
+        }
+    }
+}
+
+/// plays a sound straight away, for anything outside the rollback schedule like menus
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlaySound(pub Sfx);
+
+/// a sound is the same sound if it's the same effect from the same player on the same frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SoundKey {
+    frame: u32,
+    sound: Sfx,
+    source: usize,
+}
+
+/// sounds asked for by the rollback schedule, kept outside the rollback state so a sound that has
+/// played stays played when its frame is simulated again
+#[derive(Resource, Debug, Default)]
+struct RollbackSounds {
+    requested: Vec<SoundKey>,
+    /// the entity playing each sound, so it can be stopped if a rollback takes the sound back
+    played: BTreeMap<SoundKey, Entity>,
+}
+
+/// lets systems in the rollback schedule play sounds without repeating them on every resimulation
+#[derive(SystemParam)]
+pub struct RollbackSfx<'w> {
+    frame: Res<'w, RollbackFrame>,
+    sounds: ResMut<'w, RollbackSounds>,
+}
+
+impl RollbackSfx<'_> {
+    /// `source` tells apart the same sound from different players, usually their handle
+    pub fn play(&mut self, sound: Sfx, source: usize) {
+        let key = SoundKey {
+            frame: self.frame.0,
+            sound,
+            source,
+        };
+
+        self.sounds.requested.push(key);
+    }
+}
+
+/// the music entity and which track it's playing
+#[derive(Component, Debug)]
+struct Music(Track);
+
+fn reset_rollback_sounds(mut sounds: ResMut<RollbackSounds>) {
+    sounds.requested.clear();
+    sounds.played.clear();
+}
+
+/// swaps the music when the game moves to a part with a different track, leaving it playing
+/// across menus that share one
+fn play_state_music(
+    mut commands: Commands,
+    state: Res<State<AppState>>,
+    settings: Res<Settings>,
+    assets: Res<SoundAssets>,
+    music: Query<(Entity, &Music)>,
+) {
+    let track = Track::for_state(state.get());
+
+    if music.iter().any(|(_, music)| music.0 == track) {
+        return;
+    }
+
+    for (entity, _) in &music {
+        commands.entity(entity).despawn();
+    }
+
+    commands.spawn((
+        Music(track),
+        Persistent,
+        AudioBundle {
+            source: assets.music(track),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new(settings.music_volume)),
+        },
+    ));
+}
+
+/// the global volume only applies when a sound starts, so music that's already playing is
+/// turned up or down by hand
+fn apply_music_volume(settings: Res<Settings>, music: Query<&AudioSink, With<Music>>) {
+    for sink in &music {
+        sink.set_volume(settings.master_volume * settings.music_volume);
+    }
+}
+
+/// plays sounds the rollback schedule asked for, skipping ones a resimulated frame already
+/// played and stopping ones a resimulated frame didn't ask for again
+fn play_rollback_sounds(
+    mut commands: Commands,
+    frame: Res<RollbackFrame>,
+    simulated: Res<SimulatedFrames>,
+    mut sounds: ResMut<RollbackSounds>,
+    settings: Res<Settings>,
+    assets: Res<SoundAssets>,
+) {
+    let sounds = &mut *sounds;
+
+    let requested = sounds.requested.iter().copied().collect::<BTreeSet<_>>();
+
+    sounds.played.retain(|key, entity| {
+        let cancelled = simulated.0.contains(&key.frame) && !requested.contains(key);
+
+        // sounds that already finished have despawned themselves
+        if cancelled {
+            if let Some(entity) = commands.get_entity(*entity) {
+                entity.despawn_recursive();
+            }
+        }
+
+        !cancelled
+    });
+
+    for key in sounds.requested.drain(..) {
+        if sounds.played.contains_key(&key) {
+            continue;
+        }
+
+        let entity = spawn_sfx(&mut commands, &assets, &settings, key.sound);
+        sounds.played.insert(key, entity);
+    }
+
+    let oldest = frame.0.saturating_sub(SOUND_MEMORY);
+    sounds.played.retain(|key, _| key.frame >= oldest);
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut events: EventReader<PlaySound>,
+    settings: Res<Settings>,
+    assets: Res<SoundAssets>,
+) {
+    for PlaySound(sound) in events.read() {
+        spawn_sfx(&mut commands, &assets, &settings, *sound);
+    }
+}
+
+/// effects outlive the state that played them, so a menu's select sound isn't cut off by the
+/// screen it opens
+fn spawn_sfx(
+    commands: &mut Commands,
+    assets: &SoundAssets,
+    settings: &Settings,
+    sound: Sfx,
+) -> Entity {
+    commands
+        .spawn((
+            Persistent,
+            AudioBundle {
+                source: assets.sfx(sound),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Despawn,
+                    volume: Volume::new(settings.sfx_volume),
+                    ..default()
+                },
+            },
+        ))
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<RollbackFrame>();
+        world.init_resource::<SimulatedFrames>();
+        world.init_resource::<RollbackSounds>();
+        world.insert_resource(Settings::default());
+        world.insert_resource(SoundAssets {
+            menu_music: Handle::default(),
+            lobby_music: Handle::default(),
+            level_music: Handle::default(),
+            jump: Handle::default(),
+            land: Handle::default(),
+            climb: Handle::default(),
+            door: Handle::default(),
+            ui_move: Handle::default(),
+            ui_select: Handle::default(),
+        });
+        world
+    }
+
+    /// one update where the rollback schedule ran `frames`, each asking for the sounds with it
+    fn update(world: &mut World, frames: &[(u32, &[Sfx])]) {
+        world.resource_mut::<SimulatedFrames>().0.clear();
+
+        for (frame, sounds) in frames {
+            world.resource_mut::<RollbackFrame>().0 = *frame;
+            world.resource_mut::<SimulatedFrames>().0.push(*frame);
+
+            for &sound in *sounds {
+                world.run_system_once(move |mut sfx: RollbackSfx| sfx.play(sound, 0));
+            }
+        }
+
+        world.run_system_once(play_rollback_sounds);
+    }
+
+    fn playing(world: &mut World) -> usize {
+        world.query::<&Handle<AudioSource>>().iter(world).count()
+    }
+
+    #[test]
+    fn resimulated_sounds_play_once() {
+        let mut world = world();
+
+        update(&mut world, &[(1, &[Sfx::Jump])]);
+        // rolled back to frame 1, which jumped again
+        update(&mut world, &[(1, &[Sfx::Jump]), (2, &[])]);
+
+        assert_eq!(playing(&mut world), 1);
+    }
+
+    #[test]
+    fn sounds_a_rollback_takes_back_are_stopped() {
+        let mut world = world();
+
+        update(&mut world, &[(1, &[Sfx::Jump]), (2, &[Sfx::Land])]);
+        assert_eq!(playing(&mut world), 2);
+
+        // frame 2 ran again without landing
+        update(&mut world, &[(2, &[]), (3, &[])]);
+
+        assert_eq!(playing(&mut world), 1);
+        assert!(world
+            .resource::<RollbackSounds>()
+            .played
+            .keys()
+            .all(|key| key.sound == Sfx::Jump));
+    }
+}
@@ -0,0 +1,380 @@
+use bevy::{
+    prelude::*,
+    render::camera::{ScalingMode, Viewport},
+    transform::TransformSystem,
+};
+use bevy_ggrs::LocalPlayers;
+
+use crate::{components::Player, render::RENDER_SIZE, settings::Settings};
+
+/// world units the camera shows across at zoom 1, one 196px screen
+pub const VIEW_SIZE: f32 = 10.;
+
+/// how far players can drift from the center before the camera starts moving
+const DEAD_ZONE: Vec2 = Vec2::new(1., 0.75);
+/// space kept between the outermost players and the edge of the view
+const FRAMING_PADDING: Vec2 = Vec2::new(3., 3.);
+/// how far the camera zooms out to keep players that split up on screen
+const MAX_ZOOM: f32 = 1.5;
+/// how quickly the camera catches up to its target, higher is snappier
+const FOLLOW_RATE: f32 = 6.;
+const ZOOM_RATE: f32 = 3.;
+/// the screen merges back once both players would fit at this fraction of `MAX_ZOOM`, lower than
+/// 1 so the screen doesn't flicker between modes at the threshold
+const MERGE_ZOOM_RATIO: f32 = 0.8;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelBounds>()
+            .init_resource::<SplitScreen>()
+            .add_systems(
+                PostUpdate,
+                (update_split_screen, follow_players)
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// the camera everything is drawn through, the left half in split screen
+#[derive(Component, Debug)]
+pub struct MainCamera;
+
+/// right half of the screen, only active in split screen
+#[derive(Component, Debug)]
+pub struct SplitCamera;
+
+#[derive(Resource, Debug, Default)]
+pub struct SplitScreen {
+    pub active: bool,
+}
+
+/// world space area the current level covers, the camera never shows anything outside it
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct LevelBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for LevelBounds {
+    /// a single screen centered on the origin, like the lobby
+    fn default() -> Self {
+        Self {
+            min: Vec2::splat(-VIEW_SIZE / 2.),
+            max: Vec2::splat(VIEW_SIZE / 2.),
+        }
+    }
+}
+
+impl LevelBounds {
+    pub fn centered(center: Vec2, size: Vec2) -> Self {
+        Self {
+            min: center - size / 2.,
+            max: center + size / 2.,
+        }
+    }
+
+    /// keeps a view of `half_size` around `center` inside the level, centering on any axis
+    /// where the level is smaller than the view
+    pub fn clamp(&self, center: Vec2, half_size: Vec2) -> Vec2 {
+        let clamp_axis = |center: f32, half: f32, min: f32, max: f32| {
+            if max - min <= half * 2. {
+                (min + max) / 2.
+            } else {
+                center.clamp(min + half, max - half)
+            }
+        };
+
+        Vec2::new(
+            clamp_axis(center.x, half_size.x, self.min.x, self.max.x),
+            clamp_axis(center.y, half_size.y, self.min.y, self.max.y),
+        )
+    }
+
+    /// furthest a view of `view` size can zoom out before showing past the edges
+    fn max_zoom(&self, view: Vec2) -> f32 {
+        ((self.max - self.min) / view).min_element().max(1.)
+    }
+}
+
+/// center and zoom that fit every player in a view of `view` size with some room around them,
+/// the zoom isn't capped so callers can tell how far apart the players are
+pub fn frame_players(positions: &[Vec2], view: Vec2) -> Option<(Vec2, f32)> {
+    let first = positions.first()?;
+
+    let (min, max) = positions
+        .iter()
+        .fold((*first, *first), |(min, max), p| (min.min(*p), max.max(*p)));
+
+    let size = max - min + FRAMING_PADDING;
+    let zoom = (size / view).max_element().max(1.);
+
+    Some(((min + max) / 2., zoom))
+}
+
+/// whether players that need `zoom` to fit on one screen should get a half each, `active` being
+/// whether the screen is split already
+fn needs_split(active: bool, zoom: f32) -> bool {
+    match active {
+        true => zoom > MAX_ZOOM * MERGE_ZOOM_RATIO,
+        false => zoom > MAX_ZOOM,
+    }
+}
+
+/// moves `current` just enough that `target` is back inside the dead zone around it
+fn apply_dead_zone(current: Vec2, target: Vec2) -> Vec2 {
+    let offset = target - current;
+    let outside = (offset.abs() - DEAD_ZONE).max(Vec2::ZERO);
+
+    current + outside * offset.signum()
+}
+
+fn full_scaling() -> ScalingMode {
+    ScalingMode::Fixed {
+        width: VIEW_SIZE,
+        height: VIEW_SIZE,
+    }
+}
+
+/// half as wide for half as many pixels, so each half keeps the full screen's pixels per world
+/// unit and the pixel art stays crisp
+fn half_scaling() -> ScalingMode {
+    ScalingMode::Fixed {
+        width: VIEW_SIZE / 2.,
+        height: VIEW_SIZE,
+    }
+}
+
+/// the main camera alone, kept apart from the split camera so both can be borrowed mutably
+type MainCameraOnly = (With<MainCamera>, Without<SplitCamera>);
+type SplitCameraOnly = (With<SplitCamera>, Without<MainCamera>);
+type NotCamera = (Without<MainCamera>, Without<SplitCamera>);
+
+/// what a camera shows and where on screen it's drawn
+type ViewportQuery<'a> = (&'a mut Camera, &'a mut OrthographicProjection);
+
+/// splits the screen down the middle when two players on this machine are too far apart for one
+/// camera, an online player only has their own character to follow
+fn update_split_screen(
+    mut split: ResMut<SplitScreen>,
+    settings: Res<Settings>,
+    local_players: Option<Res<LocalPlayers>>,
+    players: Query<&Transform, With<Player>>,
+    mut main_camera: Query<ViewportQuery, MainCameraOnly>,
+    mut split_camera: Query<ViewportQuery, SplitCameraOnly>,
+) {
+    let positions = players
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect::<Vec<_>>();
+
+    let zoom = frame_players(&positions, Vec2::splat(VIEW_SIZE)).map_or(1., |(_, zoom)| zoom);
+
+    let both_local =
+        local_players.is_some_and(|local| local.0.contains(&0) && local.0.contains(&1));
+
+    let active = settings.split_screen
+        && both_local
+        && positions.len() == 2
+        && needs_split(split.active, zoom);
+
+    if split.active != active {
+        split.active = active;
+        info!("Split screen {}", if active { "on" } else { "off" });
+    }
+
+    let (Ok((mut main, mut main_projection)), Ok((mut second, mut second_projection))) =
+        (main_camera.get_single_mut(), split_camera.get_single_mut())
+    else {
+        return;
+    };
+
+    if !active {
+        if second.is_active {
+            main.viewport = None;
+            second.is_active = false;
+            main_projection.scaling_mode = full_scaling();
+        }
+
+        return;
+    }
+
+    // the cameras draw into the low resolution target, so the halves are in its pixels
+    let left = UVec2::new(RENDER_SIZE / 2, RENDER_SIZE);
+    let right = UVec2::new(RENDER_SIZE - left.x, RENDER_SIZE);
+
+    set_viewport(&mut main, UVec2::ZERO, left);
+    set_viewport(&mut second, UVec2::new(left.x, 0), right);
+
+    if !second.is_active {
+        second.is_active = true;
+        main_projection.scaling_mode = half_scaling();
+        second_projection.scaling_mode = half_scaling();
+    }
+}
+
+/// only touches the camera when the viewport actually moves, so it isn't rebuilt every frame
+fn set_viewport(camera: &mut Mut<Camera>, position: UVec2, size: UVec2) {
+    let current = camera
+        .viewport
+        .as_ref()
+        .map(|viewport| (viewport.physical_position, viewport.physical_size));
+
+    if current != Some((position, size)) {
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
+
+/// where a camera is looking and how far it's zoomed out
+type FollowQuery<'a> = (&'a mut Transform, &'a mut OrthographicProjection);
+
+fn follow_players(
+    players: Query<(&Player, &Transform), NotCamera>,
+    mut main_camera: Query<FollowQuery, MainCameraOnly>,
+    mut split_camera: Query<FollowQuery, SplitCameraOnly>,
+    split: Res<SplitScreen>,
+    bounds: Res<LevelBounds>,
+    time: Res<Time<Real>>,
+) {
+    // everyone's positions, or only the player with this handle
+    let positions = |handle: Option<usize>| {
+        players
+            .iter()
+            .filter(|(player, _)| handle.is_none_or(|handle| player.handle == handle))
+            .map(|(_, transform)| transform.translation.truncate())
+            .collect::<Vec<_>>()
+    };
+
+    let half_view = Vec2::new(VIEW_SIZE / 2., VIEW_SIZE);
+    let dt = time.delta_seconds();
+
+    if let Ok((mut transform, mut projection)) = main_camera.get_single_mut() {
+        let (positions, view) = match split.active {
+            true => (positions(Some(0)), half_view),
+            false => (positions(None), Vec2::splat(VIEW_SIZE)),
+        };
+
+        follow(
+            &mut transform,
+            &mut projection,
+            &positions,
+            view,
+            &bounds,
+            dt,
+        );
+    }
+
+    if !split.active {
+        return;
+    }
+
+    if let Ok((mut transform, mut projection)) = split_camera.get_single_mut() {
+        let positions = positions(Some(1));
+
+        // start on the second player instead of sweeping across from wherever it last was
+        if split.is_changed() {
+            if let Some(position) = positions.first() {
+                transform.translation = position.extend(transform.translation.z);
+            }
+        }
+
+        follow(
+            &mut transform,
+            &mut projection,
+            &positions,
+            half_view,
+            &bounds,
+            dt,
+        );
+    }
+}
+
+/// eases one camera towards framing `positions` in a view of `view` size
+fn follow(
+    transform: &mut Transform,
+    projection: &mut OrthographicProjection,
+    positions: &[Vec2],
+    view: Vec2,
+    bounds: &LevelBounds,
+    dt: f32,
+) {
+    // menus are drawn around the origin, so snap back there as soon as the players are gone
+    let Some((target, target_zoom)) = frame_players(positions, view) else {
+        transform.translation = Vec3::new(0., 0., transform.translation.z);
+        projection.scale = 1.;
+        return;
+    };
+
+    // never zoom out past the edges of the level
+    let target_zoom = target_zoom.min(MAX_ZOOM).min(bounds.max_zoom(view));
+
+    let zoom_t = 1. - (-ZOOM_RATE * dt).exp();
+    let zoom = projection.scale + (target_zoom - projection.scale) * zoom_t;
+
+    let current = transform.translation.truncate();
+    let target = apply_dead_zone(current, target);
+    let center = current.lerp(target, 1. - (-FOLLOW_RATE * dt).exp());
+    let center = bounds.clamp(center, view / 2. * zoom);
+
+    projection.scale = zoom;
+    transform.translation = center.extend(transform.translation.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEW: Vec2 = Vec2::splat(VIEW_SIZE);
+
+    #[test]
+    fn no_players_leave_nothing_to_frame() {
+        assert_eq!(frame_players(&[], VIEW), None);
+    }
+
+    #[test]
+    fn close_players_are_framed_at_full_size() {
+        let positions = [Vec2::new(-1., 0.), Vec2::new(1., 2.)];
+
+        assert_eq!(
+            frame_players(&positions, VIEW),
+            Some((Vec2::new(0., 1.), 1.))
+        );
+    }
+
+    #[test]
+    fn distant_players_zoom_out_to_fit_with_padding() {
+        let positions = [Vec2::new(-8., 0.), Vec2::new(8., 0.)];
+        let (center, zoom) = frame_players(&positions, VIEW).unwrap();
+
+        assert_eq!(center, Vec2::ZERO);
+        assert_eq!(zoom, (16. + FRAMING_PADDING.x) / VIEW_SIZE);
+    }
+
+    #[test]
+    fn the_screen_splits_past_max_zoom_and_merges_well_below_it() {
+        let between = MAX_ZOOM * (1. + MERGE_ZOOM_RATIO) / 2.;
+
+        assert!(!needs_split(false, MAX_ZOOM));
+        assert!(needs_split(false, MAX_ZOOM + 0.01));
+        // between the two thresholds the screen stays however it was
+        assert!(!needs_split(false, between));
+        assert!(needs_split(true, between));
+        assert!(!needs_split(true, MAX_ZOOM * MERGE_ZOOM_RATIO));
+    }
+
+    #[test]
+    fn views_stay_inside_the_level() {
+        let bounds = LevelBounds::centered(Vec2::ZERO, Vec2::new(20., 10.));
+        let half = Vec2::splat(VIEW_SIZE / 2.);
+
+        assert_eq!(bounds.clamp(Vec2::new(9., 3.), half), Vec2::new(5., 0.));
+        assert_eq!(bounds.clamp(Vec2::new(-2., 0.), half), Vec2::new(-2., 0.));
+        assert_eq!(bounds.max_zoom(VIEW), 1.);
+    }
+}
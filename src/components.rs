@@ -2,19 +2,181 @@ use std::time::Duration;
 
 use bevy::{prelude::*, time::Timer};
 
-use crate::physics::Collider;
+use crate::{input::PlayerInput, physics::Collider};
 
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Player {
     pub handle: usize,
 }
 
+/// per-character movement tuning, speeds are in world units per second
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MovementParams {
+    pub max_speed: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    /// fraction of acceleration and deceleration left while airborne
+    pub air_control: f32,
+    pub jump_impulse: f32,
+    /// fraction of the upward speed kept when jump is let go of early
+    pub jump_cut: f32,
+    pub max_fall_speed: f32,
+    pub gravity: f32,
+    pub coyote_time: f32,
+    /// how long a jump pressed before landing is remembered
+    pub jump_buffer: f32,
+}
+
+impl MovementParams {
+    pub fn nat() -> Self {
+        Self {
+            max_speed: 4.,
+            acceleration: 40.,
+            deceleration: 50.,
+            air_control: 0.6,
+            jump_impulse: 15.,
+            jump_cut: 0.5,
+            max_fall_speed: 12.,
+            gravity: -9.8 * 10.,
+            coyote_time: 0.125,
+            jump_buffer: 0.1,
+        }
+    }
+
+    pub fn aiden() -> Self {
+        Self {
+            max_speed: 3.6,
+            acceleration: 30.,
+            deceleration: 40.,
+            air_control: 0.5,
+            jump_impulse: 16.,
+            jump_cut: 0.4,
+            max_fall_speed: 13.,
+            gravity: -10.5 * 10.,
+            coyote_time: 0.1,
+            jump_buffer: 0.15,
+        }
+    }
+}
+
+/// set while a jump is still rising, so letting go of jump can cut it short
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Jumping(pub bool);
+
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Vine;
 
 #[derive(Component, Clone, Copy, Debug)]
 pub struct Platform;
 
+/// level exit, tracks how many players are standing in its trigger
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Door {
+    pub occupants: u8,
+}
+
+#[derive(Clone, Debug)]
+pub enum PlatformPath {
+    /// travels along the points at `speed` units per second, either looping back to the first
+    /// point or reversing at the last one
+    Waypoints {
+        points: Vec<Vec2>,
+        speed: f32,
+        looping: bool,
+    },
+    /// swings between `origin - extent` and `origin + extent` once every `period` seconds
+    Oscillate {
+        origin: Vec2,
+        extent: Vec2,
+        period: f32,
+    },
+}
+
+impl PlatformPath {
+    fn position_at(&self, elapsed: f32) -> Vec2 {
+        match self {
+            PlatformPath::Waypoints {
+                points,
+                speed,
+                looping,
+            } => {
+                if points.len() < 2 {
+                    return points.first().copied().unwrap_or_default();
+                }
+
+                // the closing segment back to the first point only exists on a loop
+                let closing = looping.then(|| (points[points.len() - 1], points[0]));
+                let segments = || points.windows(2).map(|w| (w[0], w[1])).chain(closing);
+
+                let length: f32 = segments().map(|(a, b)| a.distance(b)).sum();
+
+                if length <= 0. {
+                    return points[0];
+                }
+
+                let mut distance = if *looping {
+                    (elapsed * speed).rem_euclid(length)
+                } else {
+                    let d = (elapsed * speed).rem_euclid(length * 2.);
+                    if d > length {
+                        length * 2. - d
+                    } else {
+                        d
+                    }
+                };
+
+                let mut end = points[0];
+
+                for (a, b) in segments() {
+                    let segment_length = a.distance(b);
+
+                    if distance <= segment_length {
+                        return a.lerp(b, distance / segment_length.max(f32::EPSILON));
+                    }
+
+                    distance -= segment_length;
+                    end = b;
+                }
+
+                end
+            }
+            PlatformPath::Oscillate {
+                origin,
+                extent,
+                period,
+            } => {
+                if *period <= 0. {
+                    return *origin;
+                }
+
+                *origin + *extent * (elapsed / period * std::f32::consts::TAU).sin()
+            }
+        }
+    }
+}
+
+/// kinematic platform that follows a path, moved through its [`crate::physics::Velocity`] so the
+/// physics systems and riders see its motion
+#[derive(Component, Clone, Debug)]
+pub struct MovingPlatform {
+    path: PlatformPath,
+    elapsed: f32,
+}
+
+impl MovingPlatform {
+    pub fn new(path: PlatformPath) -> Self {
+        Self { path, elapsed: 0. }
+    }
+
+    pub fn tick(&mut self, duration: Duration) {
+        self.elapsed += duration.as_secs_f32();
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.path.position_at(self.elapsed)
+    }
+}
+
 #[derive(Component, Clone, Debug)]
 pub struct CoyoteTime {
     timer: Timer,
@@ -45,6 +207,73 @@ impl CoyoteTime {
     }
 }
 
+/// this and last frame's input for a player, kept in the rollback state so tapping and holding can
+/// be told apart the same way on every peer
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InputHistory {
+    current: PlayerInput,
+    previous: PlayerInput,
+}
+
+impl InputHistory {
+    pub fn push(&mut self, input: PlayerInput) {
+        self.previous = self.current;
+        self.current = input;
+    }
+
+    pub fn current(&self) -> PlayerInput {
+        self.current
+    }
+
+    pub fn pressed(&self, button: u16) -> bool {
+        self.current.pressed(button)
+    }
+
+    pub fn just_pressed(&self, button: u16) -> bool {
+        self.current.pressed(button) && !self.previous.pressed(button)
+    }
+
+    pub fn just_released(&self, button: u16) -> bool {
+        !self.current.pressed(button) && self.previous.pressed(button)
+    }
+}
+
+/// set while a player is holding on to a vine
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Climbing(pub bool);
+
+/// remembers a jump press for a short while so pressing it just before landing still jumps
+#[derive(Component, Clone, Debug)]
+pub struct JumpBuffer {
+    timer: Timer,
+}
+
+impl JumpBuffer {
+    pub fn new(buffer_secs: f32) -> Self {
+        let mut timer = Timer::from_seconds(buffer_secs, bevy::time::TimerMode::Once);
+        timer.tick(timer.duration());
+
+        Self { timer }
+    }
+
+    pub fn update(&mut self, just_pressed: bool, duration: Duration) {
+        self.timer.tick(duration);
+
+        if just_pressed {
+            self.timer.reset();
+        }
+    }
+
+    pub fn get(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    pub fn clear(&mut self) {
+        let d = self.timer.duration();
+        self.timer.tick(d.mul_f32(2.));
+    }
+}
+
 pub fn handle_coyote_time(
     mut query: Query<(&mut CoyoteTime, &Collider), With<Player>>,
     platforms: Query<&Platform>,
@@ -61,3 +290,79 @@ pub fn handle_coyote_time(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::input::{INPUT_JUMP, INPUT_LEFT};
+
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-4, "{a} != {b}");
+    }
+
+    /// history after holding `previous` then `current`
+    fn history(previous: u16, current: u16) -> InputHistory {
+        let mut history = InputHistory::default();
+        history.push(PlayerInput::new(previous, 0.));
+        history.push(PlayerInput::new(current, 0.));
+        history
+    }
+
+    #[test]
+    fn buttons_are_just_pressed_on_the_first_frame_only() {
+        assert!(history(0, INPUT_JUMP).just_pressed(INPUT_JUMP));
+        assert!(!history(INPUT_JUMP, INPUT_JUMP).just_pressed(INPUT_JUMP));
+        assert!(!history(0, INPUT_LEFT).just_pressed(INPUT_JUMP));
+    }
+
+    #[test]
+    fn buttons_are_just_released_on_the_frame_after_the_last_press() {
+        assert!(history(INPUT_JUMP, 0).just_released(INPUT_JUMP));
+        assert!(!history(INPUT_JUMP, INPUT_JUMP).just_released(INPUT_JUMP));
+        assert!(!history(0, 0).just_released(INPUT_JUMP));
+        // other buttons changing doesn't count
+        assert!(!history(INPUT_JUMP | INPUT_LEFT, INPUT_JUMP).just_released(INPUT_JUMP));
+    }
+
+    #[test]
+    fn waypoints_reverse_at_the_last_point() {
+        let path = PlatformPath::Waypoints {
+            points: vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(2., 2.)],
+            speed: 1.,
+            looping: false,
+        };
+
+        assert_near(path.position_at(1.), Vec2::new(1., 0.));
+        assert_near(path.position_at(3.), Vec2::new(2., 1.));
+        assert_near(path.position_at(5.), Vec2::new(2., 1.));
+        assert_near(path.position_at(8.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn looping_waypoints_return_to_the_first_point() {
+        let path = PlatformPath::Waypoints {
+            points: vec![Vec2::ZERO, Vec2::new(3., 0.), Vec2::new(3., 4.)],
+            speed: 1.,
+            looping: true,
+        };
+
+        // the closing segment is 5 long, making the loop 12
+        assert_near(path.position_at(9.5), Vec2::new(1.5, 2.));
+        assert_near(path.position_at(12.), Vec2::ZERO);
+        assert_near(path.position_at(13.), Vec2::new(1., 0.));
+    }
+
+    #[test]
+    fn oscillation_swings_around_the_origin() {
+        let path = PlatformPath::Oscillate {
+            origin: Vec2::new(1., 1.),
+            extent: Vec2::new(2., 0.),
+            period: 4.,
+        };
+
+        assert_near(path.position_at(0.), Vec2::new(1., 1.));
+        assert_near(path.position_at(1.), Vec2::new(3., 1.));
+        assert_near(path.position_at(3.), Vec2::new(-1., 1.));
+    }
+}
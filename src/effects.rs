@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+
+use crate::{
+    rollback::{RollbackFrame, SimulatedFrames},
+    AppState,
+};
+
+/// how many frames back a spawned effect is remembered, rollbacks never go further than this
+const EFFECT_MEMORY: u32 = 16;
+/// drawn in front of the level and the players
+const EFFECT_Z: f32 = 0.5;
+
+/// particles started by gameplay in the rollback schedule, drawn by plain entities that are never
+/// rolled back themselves
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackEffects>()
+            .add_systems(
+                Update,
+                (sync_effects, update_particles, expire_effects).chain(),
+            )
+            .add_systems(OnExit(AppState::FullLobby), reset_effects);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Effect {
+    /// kicked up when a player lands
+    Dust,
+    /// shaken off a vine while it's climbed
+    Leaves,
+    /// around a door when a player steps into it
+    Sparkles,
+}
+
+/// how one kind of effect looks, speeds are in world units per second
+struct EffectStyle {
+    count: usize,
+    color: Color,
+    size: f32,
+    lifetime: f32,
+    /// each particle's starting velocity is picked between these two
+    min_velocity: Vec2,
+    max_velocity: Vec2,
+    gravity: f32,
+}
+
+impl Effect {
+    fn style(&self) -> EffectStyle {
+        match self {
+            Effect::Dust => EffectStyle {
+                count: 6,
+                color: Color::srgb(0.75, 0.7, 0.6),
+                size: 0.1,
+                lifetime: 0.35,
+                min_velocity: Vec2::new(-1.5, 0.2),
+                max_velocity: Vec2::new(1.5, 0.8),
+                gravity: -2.,
+            },
+            Effect::Leaves => EffectStyle {
+                count: 2,
+                color: Color::srgb(0.35, 0.65, 0.25),
+                size: 0.1,
+                lifetime: 0.8,
+                min_velocity: Vec2::new(-0.6, -0.2),
+                max_velocity: Vec2::new(0.6, 0.3),
+                gravity: -0.8,
+            },
+            Effect::Sparkles => EffectStyle {
+                count: 8,
+                color: Color::srgb(1., 0.9, 0.4),
+                size: 0.08,
+                lifetime: 0.5,
+                min_velocity: Vec2::splat(-1.2),
+                max_velocity: Vec2::splat(1.2),
+                gravity: 0.,
+            },
+        }
+    }
+}
+
+/// an effect is the same effect if it's the same kind from the same player on the same frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct EffectKey {
+    frame: u32,
+    effect: Effect,
+    source: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EffectRequest {
+    key: EffectKey,
+    position: Vec2,
+}
+
+/// effects asked for by the rollback schedule and the entities drawing the ones already started,
+/// kept outside the rollback state so resimulating a frame can tell what it started last time
+#[derive(Resource, Debug, Default)]
+struct RollbackEffects {
+    requested: Vec<EffectRequest>,
+    spawned: BTreeMap<EffectKey, Entity>,
+}
+
+/// lets systems in the rollback schedule start effects without duplicating them on resimulation
+#[derive(SystemParam)]
+pub struct RollbackVfx<'w> {
+    frame: Res<'w, RollbackFrame>,
+    effects: ResMut<'w, RollbackEffects>,
+}
+
+impl RollbackVfx<'_> {
+    /// the rollback frame being simulated, for effects that repeat every few frames
+    pub fn frame(&self) -> u32 {
+        self.frame.0
+    }
+
+    /// `source` tells apart the same effect from different players, usually their handle
+    pub fn spawn(&mut self, effect: Effect, source: usize, position: Vec2) {
+        let key = EffectKey {
+            frame: self.frame.0,
+            effect,
+            source,
+        };
+
+        self.effects.requested.push(EffectRequest { key, position });
+    }
+}
+
+/// the root of one effect, its particles are children
+#[derive(Component, Debug)]
+struct EffectInstance {
+    lifetime: Timer,
+}
+
+#[derive(Component, Debug)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    lifetime: Timer,
+}
+
+/// starts effects the rollback schedule asked for, skipping ones a resimulated frame already
+/// started and cancelling ones a resimulated frame didn't ask for again
+fn sync_effects(
+    mut commands: Commands,
+    frame: Res<RollbackFrame>,
+    simulated: Res<SimulatedFrames>,
+    mut effects: ResMut<RollbackEffects>,
+) {
+    let effects = &mut *effects;
+
+    let requested = effects
+        .requested
+        .iter()
+        .map(|request| request.key)
+        .collect::<BTreeSet<_>>();
+
+    effects.spawned.retain(|key, entity| {
+        let cancelled = simulated.0.contains(&key.frame) && !requested.contains(key);
+
+        if cancelled {
+            if let Some(entity) = commands.get_entity(*entity) {
+                entity.despawn_recursive();
+            }
+        }
+
+        !cancelled
+    });
+
+    for request in effects.requested.drain(..) {
+        if effects.spawned.contains_key(&request.key) {
+            continue;
+        }
+
+        let entity = spawn_effect(&mut commands, request.key.effect, request.position);
+        effects.spawned.insert(request.key, entity);
+    }
+
+    let oldest = frame.0.saturating_sub(EFFECT_MEMORY);
+    effects.spawned.retain(|key, _| key.frame >= oldest);
+}
+
+fn spawn_effect(commands: &mut Commands, effect: Effect, position: Vec2) -> Entity {
+    let style = effect.style();
+    let mut rng = rand::thread_rng();
+
+    commands
+        .spawn((
+            EffectInstance {
+                lifetime: Timer::from_seconds(style.lifetime, TimerMode::Once),
+            },
+            SpatialBundle::from_transform(Transform::from_translation(position.extend(EFFECT_Z))),
+        ))
+        .with_children(|parent| {
+            for _ in 0..style.count {
+                let velocity = Vec2::new(
+                    rng.gen_range(style.min_velocity.x..=style.max_velocity.x),
+                    rng.gen_range(style.min_velocity.y..=style.max_velocity.y),
+                );
+
+                parent.spawn((
+                    Particle {
+                        velocity,
+                        gravity: style.gravity,
+                        lifetime: Timer::from_seconds(style.lifetime, TimerMode::Once),
+                    },
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: style.color,
+                            custom_size: Some(Vec2::splat(style.size)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            }
+        })
+        .id()
+}
+
+/// particles drift and fade out over their lifetime
+fn update_particles(
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time<Real>>,
+) {
+    let dt = time.delta_seconds();
+
+    for (mut particle, mut transform, mut sprite) in &mut particles {
+        particle.lifetime.tick(time.delta());
+        particle.velocity.y += particle.gravity * dt;
+
+        transform.translation += (particle.velocity * dt).extend(0.);
+        sprite.color.set_alpha(1. - particle.lifetime.fraction());
+    }
+}
+
+fn expire_effects(
+    mut commands: Commands,
+    mut effects: Query<(Entity, &mut EffectInstance)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut effect) in &mut effects {
+        if effect.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// the effects themselves are cleared with the rest of the level
+fn reset_effects(mut effects: ResMut<RollbackEffects>) {
+    effects.requested.clear();
+    effects.spawned.clear();
+}
@@ -0,0 +1,280 @@
+use std::{
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    replay::{ReplayPlayback, ReplayRecorder},
+    states::{
+        full_lobby::LevelCleared,
+        local_lobby::LocalPlayerCount,
+        waiting_lobby::{GameConfig, PartnerName},
+    },
+    storage,
+};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchMode {
+    Online,
+    CouchCoop,
+    Solo,
+}
+
+impl MatchMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchMode::Online => "Online",
+            MatchMode::CouchCoop => "Co-op",
+            MatchMode::Solo => "Solo",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndReason {
+    /// someone on this machine left through the menu or closed the session
+    Left,
+    /// the other peer dropped out
+    Disconnected,
+}
+
+/// one finished session, stored as a line of json in the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    /// unix time in seconds when the session started
+    pub date: u64,
+    pub mode: MatchMode,
+    /// display name of the other player, or their peer id if they didn't send one
+    pub partner: Option<String>,
+    pub seed: u32,
+    pub difficulty: u32,
+    pub levels_cleared: u32,
+    /// wall clock seconds spent in the session
+    pub duration: f32,
+    pub end_reason: EndReason,
+    /// name of the file its inputs were recorded to, `None` for games from before replays or
+    /// ones whose replay couldn't be written
+    #[serde(default)]
+    pub replay: Option<String>,
+}
+
+impl MatchRecord {
+    /// every record in the history file, oldest first
+    pub fn load_all() -> Vec<Self> {
+        let Some(path) = storage::data_file(HISTORY_FILE) else {
+            return Vec::new();
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                warn!("Failed to read {}: {e}", path.display());
+                return Vec::new();
+            }
+        };
+
+        Self::parse_all(&contents)
+    }
+
+    /// records from the lines of a history file, lines that can't be parsed are skipped
+    fn parse_all(contents: &str) -> Vec<Self> {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping bad match history entry: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn append(&self) {
+        let Some(path) = storage::data_file(HISTORY_FILE) else {
+            return;
+        };
+
+        let line = match serde_json::to_string(self) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize match record: {e}");
+                return;
+            }
+        };
+
+        match storage::append_line(&path, &line) {
+            Ok(()) => info!("Saved match to {}", path.display()),
+            Err(e) => error!("Failed to save match to {}: {e}", path.display()),
+        }
+    }
+}
+
+/// the session being played right now, written to the history when the full lobby is left
+#[derive(Resource, Debug)]
+pub struct CurrentMatch {
+    record: MatchRecord,
+    /// `Time<Real>` elapsed seconds when the session started
+    started: f32,
+}
+
+impl CurrentMatch {
+    pub fn set_end_reason(&mut self, reason: EndReason) {
+        self.record.end_reason = reason;
+    }
+}
+
+pub fn start_match(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    config: Res<GameConfig>,
+    partner: Option<Res<PartnerName>>,
+    local_player_count: Option<Res<LocalPlayerCount>>,
+) {
+    let mode = match local_player_count.map(|count| count.0) {
+        None => MatchMode::Online,
+        Some(1) => MatchMode::Solo,
+        Some(_) => MatchMode::CouchCoop,
+    };
+
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    commands.insert_resource(CurrentMatch {
+        record: MatchRecord {
+            date,
+            mode,
+            partner: partner.map(|partner| partner.0.clone()),
+            seed: config.seed,
+            difficulty: config.difficulty,
+            levels_cleared: 0,
+            duration: 0.,
+            end_reason: EndReason::Left,
+            replay: None,
+        },
+        started: time.elapsed_seconds(),
+    });
+}
+
+pub fn count_cleared_levels(
+    mut events: EventReader<LevelCleared>,
+    mut current: ResMut<CurrentMatch>,
+) {
+    for _ in events.read() {
+        current.record.levels_cleared += 1;
+    }
+}
+
+pub fn finish_match(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    current: Option<Res<CurrentMatch>>,
+    recorder: Option<Res<ReplayRecorder>>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    let Some(current) = current else {
+        return;
+    };
+
+    // watching a replay isn't a new game
+    if playback.is_none() {
+        let mut record = current.record.clone();
+        record.duration = time.elapsed_seconds() - current.started;
+        record.replay = recorder.and_then(|recorder| recorder.0.save(record.date));
+        record.append();
+    }
+
+    commands.remove_resource::<CurrentMatch>();
+}
+
+/// `YYYY-MM-DD` for a unix time in seconds, in UTC
+pub fn format_date(secs: u64) -> String {
+    // days since the epoch to a civil date, from Howard Hinnant's `civil_from_days`
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(levels_cleared: u32) -> MatchRecord {
+        MatchRecord {
+            date: 1_700_000_000,
+            mode: MatchMode::Online,
+            partner: Some("nat".into()),
+            seed: 7,
+            difficulty: 1,
+            levels_cleared,
+            duration: 61.5,
+            end_reason: EndReason::Disconnected,
+            replay: Some("1700000000.replay".into()),
+        }
+    }
+
+    #[test]
+    fn dates_convert_to_the_civil_calendar() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(86399), "1970-01-01");
+        assert_eq!(format_date(86400), "1970-01-02");
+        // leap days, including the century that is one
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+        assert_eq!(format_date(1_709_251_200), "2024-03-01");
+        assert_eq!(format_date(1_704_067_199), "2023-12-31");
+        // and the one that isn't
+        assert_eq!(format_date(4_107_456_000), "2100-02-28");
+        assert_eq!(format_date(4_107_542_400), "2100-03-01");
+    }
+
+    #[test]
+    fn records_round_trip_through_history_lines() {
+        let lines = [record(0), record(3)]
+            .iter()
+            .map(|record| serde_json::to_string(record).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(MatchRecord::parse_all(&lines), [record(0), record(3)]);
+    }
+
+    #[test]
+    fn bad_history_lines_are_skipped() {
+        let lines = format!(
+            "{}\n\nnot json\n{{\"date\": 5}}\n{}\n",
+            serde_json::to_string(&record(1)).unwrap(),
+            serde_json::to_string(&record(2)).unwrap(),
+        );
+
+        assert_eq!(MatchRecord::parse_all(&lines), [record(1), record(2)]);
+    }
+
+    #[test]
+    fn records_from_before_replays_still_load() {
+        let mut line = serde_json::to_value(record(1)).unwrap();
+        line.as_object_mut().unwrap().remove("replay");
+
+        let records = MatchRecord::parse_all(&line.to_string());
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].replay, None);
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::prelude::*;
+use bevy_ggrs::{LocalPlayers, Session};
+
+use crate::{
+    camera::VIEW_SIZE,
+    components::Player,
+    physics::Collider,
+    render::RENDER_SIZE,
+    rollback::SimulatedFrames,
+    settings::Settings,
+    states::{local_lobby::LocalPlayerCount, waiting_lobby::PartnerName},
+    AppState, Config,
+};
+
+const FONT_SIZE: f32 = 8.;
+const NAME_COLOR: Color = Color::WHITE;
+const STATS_COLOR: Color = Color::srgb(0.7, 1., 0.7);
+const STATS_BACKGROUND: Color = Color::srgba(0., 0., 0., 0.6);
+/// gap between the top of a character and their name
+const NAME_GAP: f32 = 0.2;
+
+/// names over the characters and a panel of network stats, toggled with F3
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::FullLobby), setup)
+            .add_systems(
+                Update,
+                (
+                    attach_name_tags,
+                    count_rollbacks,
+                    toggle_stats_panel,
+                    update_stats_panel,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::FullLobby)),
+            );
+    }
+}
+
+/// how much the rollback schedule has had to go back and redo since the game started
+#[derive(Resource, Debug, Default)]
+struct RollbackStats {
+    rollbacks: u32,
+    resimulated_frames: u32,
+    /// furthest frame simulated so far, anything at or before it is a resimulation
+    latest: u32,
+}
+
+/// on the player once their name has been put above them
+#[derive(Component, Debug)]
+struct NameTagged;
+
+#[derive(Component, Debug)]
+struct StatsPanel;
+
+#[derive(Component, Debug)]
+struct StatsText;
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(RollbackStats::default());
+
+    commands
+        .spawn((
+            StatsPanel,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(2.),
+                    top: Val::Px(2.),
+                    padding: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                background_color: STATS_BACKGROUND.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                StatsText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: FONT_SIZE,
+                        color: STATS_COLOR,
+                        ..default()
+                    },
+                ),
+            ));
+        });
+}
+
+/// the name a player goes by, couch players are numbered since they share one name
+fn player_name(
+    handle: usize,
+    local_players: &LocalPlayers,
+    local_player_count: Option<&LocalPlayerCount>,
+    settings: &Settings,
+    partner: Option<&PartnerName>,
+) -> String {
+    if local_player_count.is_some_and(|count| count.0 > 1) {
+        format!("P{}", handle + 1)
+    } else if local_players.0.contains(&handle) {
+        settings.display_name.clone()
+    } else {
+        partner.map_or_else(|| format!("P{}", handle + 1), |name| name.0.clone())
+    }
+}
+
+/// names are drawn in the world above each character, so they follow them into either half of
+/// a split screen
+fn attach_name_tags(
+    mut commands: Commands,
+    players: Query<(Entity, &Player, &Collider), Without<NameTagged>>,
+    local_players: Option<Res<LocalPlayers>>,
+    local_player_count: Option<Res<LocalPlayerCount>>,
+    settings: Res<Settings>,
+    partner: Option<Res<PartnerName>>,
+) {
+    // only there once the session has started
+    let Some(local_players) = local_players else {
+        return;
+    };
+
+    // text is laid out in pixels, this shrinks one of them to one pixel of the render target
+    let pixel = VIEW_SIZE / RENDER_SIZE as f32;
+
+    for (entity, player, collider) in &players {
+        let name = player_name(
+            player.handle,
+            &local_players,
+            local_player_count.as_deref(),
+            &settings,
+            partner.as_deref(),
+        );
+
+        commands
+            .entity(entity)
+            .insert(NameTagged)
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        name,
+                        TextStyle {
+                            font_size: FONT_SIZE,
+                            color: NAME_COLOR,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform {
+                        translation: Vec3::new(0., collider.half_size().y + NAME_GAP, 1.),
+                        scale: Vec3::splat(pixel),
+                        ..default()
+                    },
+                    ..default()
+                });
+            });
+    }
+}
+
+/// a rollback shows up as the schedule running a frame it has already run
+fn count_rollbacks(simulated: Res<SimulatedFrames>, mut stats: ResMut<RollbackStats>) {
+    let Some(first) = simulated.0.first() else {
+        return;
+    };
+
+    if *first <= stats.latest {
+        stats.rollbacks += 1;
+    }
+
+    let latest = stats.latest;
+    stats.resimulated_frames += simulated.0.iter().filter(|frame| **frame <= latest).count() as u32;
+    stats.latest = simulated.0.iter().copied().fold(latest, u32::max);
+}
+
+fn toggle_stats_panel(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut panels: Query<&mut Visibility, With<StatsPanel>>,
+) {
+    if !keys.just_pressed(KeyCode::F3) {
+        return;
+    }
+
+    for mut visibility in &mut panels {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_stats_panel(
+    session: Option<Res<Session<Config>>>,
+    stats: Res<RollbackStats>,
+    panels: Query<&Visibility, With<StatsPanel>>,
+    mut texts: Query<&mut Text, With<StatsText>>,
+) {
+    // nothing to work out while the panel is hidden
+    if panels
+        .iter()
+        .all(|visibility| *visibility == Visibility::Hidden)
+    {
+        return;
+    }
+
+    let mut lines = Vec::new();
+
+    match session.as_deref() {
+        Some(Session::P2P(session)) => {
+            for handle in session.remote_player_handles() {
+                match session.network_stats(handle) {
+                    Ok(network) => {
+                        lines.push(format!("P{} ping {}ms", handle + 1, network.ping));
+                        lines.push(format!("queue {}", network.send_queue_len));
+                        lines.push(format!(
+                            "behind {}/{}",
+                            network.local_frames_behind, network.remote_frames_behind
+                        ));
+                    }
+                    // stats need a few seconds of traffic before there's anything to show
+                    Err(_) => lines.push(format!("P{} waiting", handle + 1)),
+                }
+            }
+        }
+        Some(_) => lines.push("local".into()),
+        None => {}
+    }
+
+    lines.push(format!(
+        "rollbacks {} ({}f)",
+        stats.rollbacks, stats.resimulated_frames
+    ));
+
+    let value = lines.join("\n");
+
+    for mut text in &mut texts {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            display_name: "nat".into(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn solo_players_go_by_their_display_name() {
+        let local = LocalPlayers(vec![0]);
+
+        let name = player_name(0, &local, Some(&LocalPlayerCount(1)), &settings(), None);
+
+        assert_eq!(name, "nat");
+    }
+
+    #[test]
+    fn couch_players_are_numbered() {
+        let local = LocalPlayers(vec![0, 1]);
+        let count = LocalPlayerCount(2);
+
+        assert_eq!(
+            player_name(0, &local, Some(&count), &settings(), None),
+            "P1"
+        );
+        assert_eq!(
+            player_name(1, &local, Some(&count), &settings(), None),
+            "P2"
+        );
+    }
+
+    #[test]
+    fn online_partners_go_by_the_name_they_sent() {
+        let local = LocalPlayers(vec![1]);
+        let partner = PartnerName("aiden".into());
+
+        assert_eq!(
+            player_name(1, &local, None, &settings(), Some(&partner)),
+            "nat"
+        );
+        assert_eq!(
+            player_name(0, &local, None, &settings(), Some(&partner)),
+            "aiden"
+        );
+        assert_eq!(player_name(0, &local, None, &settings(), None), "P1");
+    }
+}
@@ -1,80 +1,647 @@
-use bevy::{prelude::*, utils::HashMap};
-use bevy_ggrs::{LocalInputs, LocalPlayers};
+use std::collections::BTreeMap;
 
-use crate::{resources::WindowScale, MIN_WINDOW_SIZE};
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_ggrs::{LocalInputs, LocalPlayers, PlayerInputs};
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
 
-pub const INPUT_LEFT: u8 = 1 << 0;
-pub const INPUT_RIGHT: u8 = 1 << 1;
-pub const INPUT_JUMP: u8 = 1 << 2;
-pub const INPUT_INTERACT: u8 = 1 << 3;
+use crate::{
+    components::{InputHistory, Player},
+    resources::WindowScale,
+    settings::Settings,
+    storage, Config,
+};
+
+pub const INPUT_LEFT: u16 = 1 << 0;
+pub const INPUT_RIGHT: u16 = 1 << 1;
+pub const INPUT_JUMP: u16 = 1 << 2;
+pub const INPUT_INTERACT: u16 = 1 << 3;
+pub const INPUT_DOWN: u16 = 1 << 4;
+pub const INPUT_CROUCH: u16 = 1 << 5;
+pub const INPUT_DASH: u16 = 1 << 6;
+pub const INPUT_EMOTE: u16 = 1 << 7;
+pub const INPUT_PAUSE_VOTE: u16 = 1 << 8;
+
+const BINDINGS_FILE: &str = "bindings.ron";
+
+/// stick deflection below which the stick counts as centered
+const STICK_DEADZONE: f32 = 0.2;
+/// stick deflection needed to count as a d-pad press, for menus and the down button
+const STICK_PRESS: f32 = 0.6;
+
+/// one frame of a player's input as it's sent to the other peer, 4 bytes
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Pod, Zeroable, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub buttons: u16,
+    /// horizontal stick position quantized to -127..=127, 0 when only digital input is used
+    pub axis_x: i8,
+    _padding: u8,
+}
+
+impl PlayerInput {
+    pub fn new(buttons: u16, axis_x: f32) -> Self {
+        Self {
+            buttons,
+            axis_x: (axis_x.clamp(-1., 1.) * i8::MAX as f32).round() as i8,
+            _padding: 0,
+        }
+    }
+
+    pub fn pressed(&self, button: u16) -> bool {
+        self.buttons & button != 0
+    }
+
+    /// horizontal movement in -1..=1, the analog axis wins over the digital buttons when it's used
+    pub fn horizontal(&self) -> f32 {
+        if self.axis_x != 0 {
+            return (self.axis_x as f32 / i8::MAX as f32).clamp(-1., 1.);
+        }
+
+        let mut horizontal = 0.;
+
+        if self.pressed(INPUT_LEFT) {
+            horizontal -= 1.;
+        }
+
+        if self.pressed(INPUT_RIGHT) {
+            horizontal += 1.;
+        }
+
+        horizontal
+    }
+}
+
+/// gamepads in the order they were plugged in, the first one plays alongside the keyboard and in
+/// couch co-op the n-th one belongs to player n
+#[derive(Resource, Debug, Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+/// menu navigation from every connected gamepad this frame, read by the menus next to the keyboard
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct GamepadMenuInput {
+    pub previous: bool,
+    pub next: bool,
+    pub confirm: bool,
+    pub back: bool,
+    /// d-pad left and right only, for changing a value rather than moving through a list
+    pub left: bool,
+    pub right: bool,
+    stick: i8,
+}
+
+pub fn handle_gamepad_connections(
+    mut events: EventReader<GamepadConnectionEvent>,
+    mut gamepads: ResMut<ConnectedGamepads>,
+) {
+    for event in events.read() {
+        match &event.connection {
+            GamepadConnection::Connected(info) => {
+                if !gamepads.0.contains(&event.gamepad) {
+                    gamepads.0.push(event.gamepad);
+                }
+                info!("Gamepad {} connected: {}", event.gamepad.id, info.name);
+            }
+            GamepadConnection::Disconnected => {
+                gamepads.0.retain(|g| *g != event.gamepad);
+                info!("Gamepad {} disconnected", event.gamepad.id);
+            }
+        }
+    }
+}
+
+pub fn read_gamepad_menu_input(
+    mut menu: ResMut<GamepadMenuInput>,
+    gamepads: Res<ConnectedGamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    let just_pressed = |button_type| {
+        gamepads
+            .0
+            .iter()
+            .any(|g| buttons.just_pressed(GamepadButton::new(*g, button_type)))
+    };
+
+    // the stick only counts once per push, it has to come back to the middle to repeat
+    let stick = gamepads
+        .0
+        .iter()
+        .map(|g| {
+            let x = axes.get(GamepadAxis::new(*g, GamepadAxisType::LeftStickX));
+            let y = axes.get(GamepadAxis::new(*g, GamepadAxisType::LeftStickY));
+            let (x, y) = (x.unwrap_or(0.), y.unwrap_or(0.));
+
+            if y > STICK_PRESS || x < -STICK_PRESS {
+                -1
+            } else if y < -STICK_PRESS || x > STICK_PRESS {
+                1
+            } else {
+                0
+            }
+        })
+        .find(|d| *d != 0)
+        .unwrap_or(0);
+
+    let stick_moved = stick != menu.stick;
+    menu.stick = stick;
+
+    menu.previous = just_pressed(GamepadButtonType::DPadUp)
+        || just_pressed(GamepadButtonType::DPadLeft)
+        || (stick_moved && stick < 0);
+    menu.next = just_pressed(GamepadButtonType::DPadDown)
+        || just_pressed(GamepadButtonType::DPadRight)
+        || (stick_moved && stick > 0);
+    menu.confirm = just_pressed(GamepadButtonType::South) || just_pressed(GamepadButtonType::Start);
+    menu.back = just_pressed(GamepadButtonType::East) || just_pressed(GamepadButtonType::Select);
+    menu.left = just_pressed(GamepadButtonType::DPadLeft);
+    menu.right = just_pressed(GamepadButtonType::DPadRight);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Interact,
+    Down,
+    Crouch,
+    Dash,
+    Emote,
+    PauseVote,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Left,
+        Action::Right,
+        Action::Jump,
+        Action::Interact,
+        Action::Down,
+        Action::Crouch,
+        Action::Dash,
+        Action::Emote,
+        Action::PauseVote,
+    ];
+
+    pub fn button(&self) -> u16 {
+        match self {
+            Action::Left => INPUT_LEFT,
+            Action::Right => INPUT_RIGHT,
+            Action::Jump => INPUT_JUMP,
+            Action::Interact => INPUT_INTERACT,
+            Action::Down => INPUT_DOWN,
+            Action::Crouch => INPUT_CROUCH,
+            Action::Dash => INPUT_DASH,
+            Action::Emote => INPUT_EMOTE,
+            Action::PauseVote => INPUT_PAUSE_VOTE,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Jump => "Jump",
+            Action::Interact => "Interact",
+            Action::Down => "Down",
+            Action::Crouch => "Crouch",
+            Action::Dash => "Dash",
+            Action::Emote => "Emote",
+            Action::PauseVote => "Pause",
+        }
+    }
+}
+
+/// how many keys and how many gamepad buttons each action can have
+pub const BINDING_SLOTS: usize = 2;
+
+/// binds `input` to a slot of `action` in `bindings[target]`, a slot past the last binding adds a
+/// new one
+///
+/// an input can only do one thing across all of `bindings`, so wherever it was bound before gets
+/// the input this slot had, or loses it when the slot was empty
+fn bind<T: Copy + PartialEq>(
+    bindings: &mut [&mut BTreeMap<Action, Vec<T>>],
+    target: usize,
+    action: Action,
+    slot: usize,
+    input: T,
+) {
+    let replaced = bindings[target]
+        .get(&action)
+        .and_then(|inputs| inputs.get(slot))
+        .copied();
+
+    for inputs in bindings.iter_mut().flat_map(|map| map.values_mut()) {
+        let Some(i) = inputs.iter().position(|bound| *bound == input) else {
+            continue;
+        };
+
+        match replaced {
+            Some(replaced) => inputs[i] = replaced,
+            None => {
+                inputs.remove(i);
+            }
+        }
+    }
+
+    let inputs = bindings[target].entry(action).or_default();
+
+    match inputs.get_mut(slot) {
+        Some(bound) => *bound = input,
+        None => inputs.push(input),
+    }
+}
+
+/// how many players can share the keyboard in couch co-op
+pub const COUCH_PLAYERS: usize = 2;
+
+/// which set of keys is meant, playing alone or one half of a shared keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyboard {
+    Solo,
+    /// the keys of the couch co-op player with this handle
+    Couch(usize),
+}
+
+impl Keyboard {
+    pub const ALL: [Keyboard; 1 + COUCH_PLAYERS] =
+        [Keyboard::Solo, Keyboard::Couch(0), Keyboard::Couch(1)];
+
+    pub fn name(&self) -> String {
+        match self {
+            Keyboard::Solo => "Solo".into(),
+            Keyboard::Couch(handle) => format!("Couch P{}", handle + 1),
+        }
+    }
+}
+
+/// keys and gamepad buttons bound to each action, persisted in the config dir
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+    /// each couch co-op player's half of the keyboard, by handle
+    couch_keys: [BTreeMap<Action, Vec<KeyCode>>; COUCH_PLAYERS],
+    gamepad: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let keys = BTreeMap::from([
+            (Action::Left, vec![KeyCode::ArrowLeft, KeyCode::KeyA]),
+            (Action::Right, vec![KeyCode::ArrowRight, KeyCode::KeyD]),
+            (Action::Jump, vec![KeyCode::KeyW, KeyCode::ArrowUp]),
+            (Action::Interact, vec![KeyCode::Space, KeyCode::KeyE]),
+            (Action::Down, vec![KeyCode::KeyS, KeyCode::ArrowDown]),
+            (Action::Crouch, vec![KeyCode::KeyC, KeyCode::ControlRight]),
+            (Action::Dash, vec![KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            (Action::Emote, vec![KeyCode::KeyQ]),
+            (Action::PauseVote, vec![KeyCode::KeyP]),
+        ]);
+
+        // the first couch player gets the left hand keys and the second the arrows
+        let couch_keys = [
+            BTreeMap::from([
+                (Action::Left, vec![KeyCode::KeyA]),
+                (Action::Right, vec![KeyCode::KeyD]),
+                (Action::Jump, vec![KeyCode::KeyW]),
+                (Action::Interact, vec![KeyCode::KeyE]),
+                (Action::Down, vec![KeyCode::KeyS]),
+                (Action::Crouch, vec![KeyCode::KeyC]),
+                (Action::Dash, vec![KeyCode::ShiftLeft]),
+                (Action::Emote, vec![KeyCode::KeyQ]),
+                (Action::PauseVote, vec![KeyCode::KeyP]),
+            ]),
+            BTreeMap::from([
+                (Action::Left, vec![KeyCode::ArrowLeft]),
+                (Action::Right, vec![KeyCode::ArrowRight]),
+                (Action::Jump, vec![KeyCode::ArrowUp]),
+                (Action::Interact, vec![KeyCode::Enter]),
+                (Action::Down, vec![KeyCode::ArrowDown]),
+                (Action::Crouch, vec![KeyCode::ControlRight]),
+                (Action::Dash, vec![KeyCode::ShiftRight]),
+                (Action::Emote, vec![KeyCode::Slash]),
+                (Action::PauseVote, vec![KeyCode::Backspace]),
+            ]),
+        ];
+
+        let gamepad = BTreeMap::from([
+            (Action::Left, vec![GamepadButtonType::DPadLeft]),
+            (Action::Right, vec![GamepadButtonType::DPadRight]),
+            (Action::Jump, vec![GamepadButtonType::South]),
+            (Action::Interact, vec![GamepadButtonType::West]),
+            (Action::Down, vec![GamepadButtonType::DPadDown]),
+            (Action::Crouch, vec![GamepadButtonType::LeftTrigger]),
+            (Action::Dash, vec![GamepadButtonType::East]),
+            (Action::Emote, vec![GamepadButtonType::North]),
+            (Action::PauseVote, vec![GamepadButtonType::Start]),
+        ]);
+
+        Self {
+            keys,
+            couch_keys,
+            gamepad,
+        }
+    }
+}
+
+impl InputMap {
+    /// the saved bindings, or the defaults when there are none
+    pub fn load() -> Self {
+        storage::config_file(BINDINGS_FILE)
+            .and_then(|path| storage::load_ron(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = storage::config_file(BINDINGS_FILE) else {
+            return;
+        };
+
+        match storage::save_ron(&path, self) {
+            Ok(()) => info!("Saved key bindings to {}", path.display()),
+            Err(e) => error!("Failed to save key bindings to {}: {e}", path.display()),
+        }
+    }
+
+    fn keyboard(&self, keyboard: Keyboard) -> &BTreeMap<Action, Vec<KeyCode>> {
+        match keyboard {
+            Keyboard::Solo => &self.keys,
+            Keyboard::Couch(handle) => &self.couch_keys[handle.min(COUCH_PLAYERS - 1)],
+        }
+    }
+
+    pub fn keys(&self, keyboard: Keyboard, action: Action) -> &[KeyCode] {
+        self.keyboard(keyboard)
+            .get(&action)
+            .map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn gamepad_buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.gamepad
+            .get(&action)
+            .map_or(&[], |buttons| buttons.as_slice())
+    }
+
+    /// puts `key` in one of the action's key slots, see [`bind`] for what happens to a key that
+    /// was already in use, couch players share one keyboard so a key can't belong to both
+    pub fn bind_key(&mut self, keyboard: Keyboard, action: Action, slot: usize, key: KeyCode) {
+        match keyboard {
+            Keyboard::Solo => bind(&mut [&mut self.keys], 0, action, slot, key),
+            Keyboard::Couch(handle) => {
+                let [first, second] = &mut self.couch_keys;
+                bind(
+                    &mut [first, second],
+                    handle.min(COUCH_PLAYERS - 1),
+                    action,
+                    slot,
+                    key,
+                );
+            }
+        }
+    }
+
+    pub fn bind_gamepad_button(&mut self, action: Action, slot: usize, button: GamepadButtonType) {
+        bind(&mut [&mut self.gamepad], 0, action, slot, button);
+    }
+
+    pub fn keyboard_buttons(&self, keyboard: Keyboard, keys: &ButtonInput<KeyCode>) -> u16 {
+        Action::ALL
+            .iter()
+            .filter(|action| keys.any_pressed(self.keys(keyboard, **action).iter().copied()))
+            .fold(0, |input, action| input | action.button())
+    }
+
+    /// buttons and stick position of a gamepad, the stick is 0 inside the deadzone so the d-pad
+    /// can take over
+    pub fn gamepad_input(
+        &self,
+        gamepad: Gamepad,
+        buttons: &ButtonInput<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> (u16, f32) {
+        let mut input = Action::ALL
+            .iter()
+            .filter(|action| {
+                self.gamepad_buttons(**action)
+                    .iter()
+                    .any(|b| buttons.pressed(GamepadButton::new(gamepad, *b)))
+            })
+            .fold(0, |input, action| input | action.button());
+
+        let x = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or(0.);
+        let y = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+            .unwrap_or(0.);
+
+        if y < -STICK_PRESS {
+            input |= INPUT_DOWN;
+        }
+
+        let x = if x.abs() < STICK_DEADZONE { 0. } else { x };
+
+        (input, x)
+    }
+}
 
 pub fn read_local_inputs(
     mut commands: Commands,
+    input_map: Res<InputMap>,
     keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<ConnectedGamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
     local_players: Res<LocalPlayers>,
 ) {
     let mut local_inputs = HashMap::new();
 
+    // with more than one player on this machine each gets half the keyboard and their own gamepad
+    let shared = local_players.0.len() > 1;
+
     for handle in &local_players.0 {
-        let mut input = 0u8;
+        let (keyboard, gamepad) = match shared {
+            true => (Keyboard::Couch(*handle), gamepads.0.get(*handle)),
+            false => (Keyboard::Solo, gamepads.0.first()),
+        };
 
-        if keys.any_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
-            input |= INPUT_LEFT
-        }
-        if keys.any_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
-            input |= INPUT_RIGHT;
-        }
-        if keys.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
-            input |= INPUT_JUMP;
-        }
-        if keys.any_pressed([KeyCode::Space, KeyCode::KeyE]) {
-            input |= INPUT_INTERACT;
+        let mut input = input_map.keyboard_buttons(keyboard, &keys);
+        let mut axis = 0.;
+
+        if let Some(gamepad) = gamepad {
+            let (buttons, x) = input_map.gamepad_input(*gamepad, &gamepad_buttons, &gamepad_axes);
+            input |= buttons;
+            axis = x;
         }
 
-        local_inputs.insert(*handle, input);
+        local_inputs.insert(*handle, PlayerInput::new(input, axis));
     }
 
-    commands.insert_resource(LocalInputs::<super::Config>(local_inputs));
+    commands.insert_resource(LocalInputs::<Config>(local_inputs));
+}
+
+pub fn update_input_history(
+    mut players: Query<(&Player, &mut InputHistory)>,
+    inputs: Res<PlayerInputs<Config>>,
+) {
+    for (player, mut history) in &mut players {
+        let Some((input, _)) = inputs.get(player.handle) else {
+            continue;
+        };
+
+        history.push(*input);
+    }
 }
 
 pub fn handle_window_resize(
     keys: Res<ButtonInput<KeyCode>>,
-    mut windows: Query<&mut Window>,
     mut resolution_settings: ResMut<WindowScale>,
 ) {
-    let mut window = windows.single_mut();
-
     if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::Equal) {
-        let scale = resolution_settings.increase();
-        window.resolution.set(
-            MIN_WINDOW_SIZE * scale as f32,
-            MIN_WINDOW_SIZE * scale as f32,
-        )
+        resolution_settings.increase();
     }
 
     if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::Minus) {
-        let scale = resolution_settings.decrease();
-        window.resolution.set(
-            MIN_WINDOW_SIZE * scale as f32,
-            MIN_WINDOW_SIZE * scale as f32,
-        )
+        resolution_settings.decrease();
     }
 }
 
-pub fn direction(input: u8) -> Vec2 {
-    let mut direction = Vec2::ZERO;
+/// F11 switches between the window and borderless fullscreen, the picture is letterboxed so the
+/// art keeps its shape either way
+pub fn toggle_fullscreen(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+
+    settings.fullscreen = !settings.fullscreen;
+    settings.save();
+}
+
+/// direction the player is asking for with each axis in -1..=1, scaled by their `MovementParams`
+/// when moving
+pub fn direction(input: PlayerInput) -> Vec2 {
+    let mut direction = Vec2::new(input.horizontal(), 0.);
 
-    if input & INPUT_LEFT != 0 {
-        direction.x -= 4.;
+    if input.pressed(INPUT_JUMP) {
+        direction.y += 1.;
     }
 
-    if input & INPUT_RIGHT != 0 {
-        direction.x += 4.;
+    direction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_a_slot_keeps_the_other_slot() {
+        let mut map = InputMap::default();
+
+        map.bind_key(Keyboard::Solo, Action::Jump, 1, KeyCode::KeyK);
+
+        assert_eq!(
+            map.keys(Keyboard::Solo, Action::Jump),
+            [KeyCode::KeyW, KeyCode::KeyK]
+        );
     }
 
-    if input & INPUT_JUMP != 0 {
-        direction.y += 15.;
+    #[test]
+    fn taking_a_key_from_another_action_swaps_them() {
+        let mut map = InputMap::default();
+
+        map.bind_key(Keyboard::Solo, Action::Jump, 0, KeyCode::Space);
+
+        assert_eq!(
+            map.keys(Keyboard::Solo, Action::Jump),
+            [KeyCode::Space, KeyCode::ArrowUp]
+        );
+        assert_eq!(
+            map.keys(Keyboard::Solo, Action::Interact),
+            [KeyCode::KeyW, KeyCode::KeyE]
+        );
     }
 
-    direction
+    #[test]
+    fn moving_a_key_between_slots_of_one_action_swaps_them() {
+        let mut map = InputMap::default();
+
+        map.bind_key(Keyboard::Solo, Action::Left, 0, KeyCode::KeyA);
+
+        assert_eq!(
+            map.keys(Keyboard::Solo, Action::Left),
+            [KeyCode::KeyA, KeyCode::ArrowLeft]
+        );
+    }
+
+    #[test]
+    fn a_key_bound_into_an_empty_slot_leaves_its_old_action() {
+        let mut map = InputMap::default();
+
+        map.bind_gamepad_button(Action::Jump, 1, GamepadButtonType::West);
+
+        assert_eq!(
+            map.gamepad_buttons(Action::Jump),
+            [GamepadButtonType::South, GamepadButtonType::West]
+        );
+        assert!(map.gamepad_buttons(Action::Interact).is_empty());
+    }
+
+    #[test]
+    fn couch_players_cannot_share_a_key() {
+        let mut map = InputMap::default();
+
+        map.bind_key(Keyboard::Couch(1), Action::Jump, 0, KeyCode::KeyW);
+
+        assert_eq!(map.keys(Keyboard::Couch(1), Action::Jump), [KeyCode::KeyW]);
+        assert_eq!(
+            map.keys(Keyboard::Couch(0), Action::Jump),
+            [KeyCode::ArrowUp]
+        );
+        // playing alone keeps its own keys
+        assert_eq!(
+            map.keys(Keyboard::Solo, Action::Jump),
+            [KeyCode::KeyW, KeyCode::ArrowUp]
+        );
+    }
+
+    #[test]
+    fn bindings_without_couch_keys_load_the_default_ones() {
+        let map: InputMap = ron::from_str("(keys: {Jump: [KeyK]})").unwrap();
+
+        assert_eq!(map.keys(Keyboard::Solo, Action::Jump), [KeyCode::KeyK]);
+        assert_eq!(map.keys(Keyboard::Couch(0), Action::Jump), [KeyCode::KeyW]);
+    }
+
+    #[test]
+    fn every_button_reaches_the_input() {
+        let map = InputMap::default();
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyC);
+        keys.press(KeyCode::ShiftLeft);
+        keys.press(KeyCode::KeyP);
+
+        let buttons = map.keyboard_buttons(Keyboard::Solo, &keys);
+
+        assert_eq!(buttons, INPUT_CROUCH | INPUT_DASH | INPUT_PAUSE_VOTE);
+    }
+
+    #[test]
+    fn couch_players_have_their_own_key_for_every_action() {
+        let map = InputMap::default();
+
+        for action in Action::ALL {
+            let first = map.keys(Keyboard::Couch(0), action);
+            let second = map.keys(Keyboard::Couch(1), action);
+
+            assert!(!first.is_empty() && !second.is_empty(), "{action:?}");
+            assert!(first.iter().all(|key| !second.contains(key)), "{action:?}");
+        }
+    }
 }
@@ -1,37 +1,93 @@
 use bevy::prelude::*;
-use bevy_ggrs::PlayerInputs;
 
 use crate::{
-    components::{Player, Vine},
+    audio::{RollbackSfx, Sfx},
+    components::{Climbing, Door, InputHistory, Player, Vine},
+    effects::{Effect, RollbackVfx},
+    input::INPUT_INTERACT,
     physics::{self, Collider, Gravity, Velocity},
-    Config,
 };
 
+/// frames between handfuls of leaves falling off a vine that's being climbed
+const LEAF_INTERVAL: u32 = 12;
+
 pub fn handle_vine_interactions(
-    mut players: Query<(&mut Velocity, &Collider, &Player, &mut Gravity)>,
-    vines: Query<(&Vine, Entity)>,
-    inputs: Res<PlayerInputs<Config>>,
+    mut players: Query<(
+        &Player,
+        &mut Velocity,
+        &Collider,
+        &InputHistory,
+        &mut Climbing,
+        &mut Gravity,
+        &Transform,
+    )>,
+    vines: Query<&Vine>,
+    mut sfx: RollbackSfx,
+    mut vfx: RollbackVfx,
 ) {
-    for (mut velocity, collider, player, mut gravity) in &mut players {
-        let input = inputs.get(player.handle);
+    for (player, mut velocity, collider, history, mut climbing, mut gravity, transform) in
+        &mut players
+    {
+        let on_vine = collider
+            .colliding_entities()
+            .any(|entity| vines.contains(entity));
+
+        // holding interact on a vine climbs it, letting go drops off it
+        let was_climbing = climbing.0;
+
+        climbing.0 = on_vine && history.pressed(INPUT_INTERACT);
+
+        if climbing.0 && !was_climbing {
+            sfx.play(Sfx::Climb, player.handle);
+        }
+
+        // letting go shakes a last handful of leaves loose
+        let let_go = was_climbing && on_vine && history.just_released(INPUT_INTERACT);
+
+        if let_go || (climbing.0 && (!was_climbing || vfx.frame().is_multiple_of(LEAF_INTERVAL))) {
+            vfx.spawn(
+                Effect::Leaves,
+                player.handle,
+                transform.translation.truncate(),
+            );
+        }
 
-        if input.is_none() {
+        if !on_vine {
             continue;
         }
 
-        let (input, _) = *input.unwrap();
-
-        for (_, entity) in &vines {
-            if collider.colliding_with(&entity).is_some() {
-                if input & crate::input::INPUT_INTERACT != 0 {
-                    velocity.0.y = 1.5;
-                    gravity.temp_override();
-                    continue;
-                } else if !collider.check_colliding_solid_side(physics::CollidingSide::Bottom) {
-                    velocity.0.y = -0.75;
-                    gravity.temp_override();
-                    continue;
-                }
+        if climbing.0 {
+            velocity.0.y = 1.5;
+            gravity.temp_override();
+        } else if !collider.check_colliding_solid_side(physics::CollidingSide::Bottom) {
+            velocity.0.y = -0.75;
+            gravity.temp_override();
+        }
+    }
+}
+
+pub fn handle_door_triggers(
+    players: Query<(&Player, &Collider)>,
+    mut doors: Query<(&mut Door, &Transform)>,
+    mut sfx: RollbackSfx,
+    mut vfx: RollbackVfx,
+) {
+    for (player, collider) in &players {
+        for entity in collider.started_colliding() {
+            if let Ok((mut door, transform)) = doors.get_mut(entity) {
+                door.occupants += 1;
+                sfx.play(Sfx::Door, player.handle);
+                vfx.spawn(
+                    Effect::Sparkles,
+                    player.handle,
+                    transform.translation.truncate(),
+                );
+            }
+        }
+
+        for entity in collider.stopped_colliding() {
+            if let Ok((mut door, _)) = doors.get_mut(entity) {
+                door.occupants = door.occupants.saturating_sub(1);
             }
         }
     }
@@ -1,6 +1,90 @@
-#[allow(unused)]
-const FLOOR_HEX: u32 = 0x000000;
-#[allow(unused)]
-const DOOR_HEX: u32 = 0x700000;
-#[allow(unused)]
-const VINE_HEX: u32 = 0x00A000;
+use bevy::prelude::*;
+
+use crate::physics::PhysicsMaterial;
+
+/// the only level so far, the lobby both players start in
+pub const LOBBY_LEVEL: u32 = 0;
+
+/// world units the lobby covers, one screen centered on the origin
+pub const LOBBY_SIZE: Vec2 = Vec2::new(10., 10.);
+
+pub const FLOOR_HEX: u32 = 0x000000;
+pub const DOOR_HEX: u32 = 0x700000;
+pub const VINE_HEX: u32 = 0x00A000;
+const ICE_HEX: u32 = 0xA0E0FF;
+const BOUNCY_HEX: u32 = 0xE040A0;
+const MUD_HEX: u32 = 0x604020;
+
+/// what a colour in the level art turns into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    /// solid ground, with a surface material for anything other than plain floor
+    Floor(Option<PhysicsMaterial>),
+    Door,
+    Vine,
+}
+
+impl Tile {
+    /// `None` for colours that are only decoration
+    pub fn from_hex(hex: u32) -> Option<Self> {
+        match hex {
+            DOOR_HEX => Some(Tile::Door),
+            VINE_HEX => Some(Tile::Vine),
+            FLOOR_HEX | ICE_HEX | BOUNCY_HEX | MUD_HEX => Some(Tile::Floor(material_for_hex(hex))),
+            _ => None,
+        }
+    }
+}
+
+/// an axis-aligned block of one colour in the level art, in world units
+#[derive(Debug, Clone, Copy)]
+pub struct LevelRect {
+    pub hex: u32,
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl LevelRect {
+    pub fn tile(&self) -> Option<Tile> {
+        Tile::from_hex(self.hex)
+    }
+}
+
+/// surface material for a floor pixel, plain floor and anything that isn't a floor have none
+fn material_for_hex(hex: u32) -> Option<PhysicsMaterial> {
+    match hex {
+        ICE_HEX => Some(PhysicsMaterial::ICE),
+        BOUNCY_HEX => Some(PhysicsMaterial::BOUNCY),
+        MUD_HEX => Some(PhysicsMaterial::MUD),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floor_colours_carry_their_material() {
+        assert_eq!(Tile::from_hex(FLOOR_HEX), Some(Tile::Floor(None)));
+        assert_eq!(
+            Tile::from_hex(ICE_HEX),
+            Some(Tile::Floor(Some(PhysicsMaterial::ICE)))
+        );
+        assert_eq!(
+            Tile::from_hex(BOUNCY_HEX),
+            Some(Tile::Floor(Some(PhysicsMaterial::BOUNCY)))
+        );
+        assert_eq!(
+            Tile::from_hex(MUD_HEX),
+            Some(Tile::Floor(Some(PhysicsMaterial::MUD)))
+        );
+    }
+
+    #[test]
+    fn doors_vines_and_decoration_have_no_material() {
+        assert_eq!(Tile::from_hex(DOOR_HEX), Some(Tile::Door));
+        assert_eq!(Tile::from_hex(VINE_HEX), Some(Tile::Vine));
+        assert_eq!(Tile::from_hex(0x123456), None);
+    }
+}
@@ -1,33 +1,68 @@
+use animation::AnimationPlugin;
 use args::Args;
+use audio::SoundPlugin;
 use bevy::ecs::system::SystemId;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
-use bevy::window::EnabledButtons;
-use bevy::{prelude::*, window::WindowResolution};
+use bevy::window::{EnabledButtons, WindowResizeConstraints};
 use bevy_ggrs::*;
 use bevy_matchbox::matchbox_socket::PeerId;
 use bevy_matchbox::prelude::{MultipleChannels, SingleChannel};
 use bevy_matchbox::{CloseSocketExt, MatchboxSocket};
 use bevy_roll_safe::RollApp;
+use camera::{CameraPlugin, MainCamera, SplitCamera};
 use clap::Parser;
-use components::{CoyoteTime, Player};
-use input::handle_window_resize;
+use components::{
+    Climbing, CoyoteTime, Door, InputHistory, JumpBuffer, Jumping, MovementParams, MovingPlatform,
+    Player,
+};
+use effects::EffectsPlugin;
+use hud::HudPlugin;
+use input::{
+    handle_gamepad_connections, handle_window_resize, read_gamepad_menu_input, toggle_fullscreen,
+    ConnectedGamepads, GamepadMenuInput, InputMap,
+};
 use physics::PhysicsPlugin;
+use render::{setup_pixel_target, PixelPerfectPlugin, PixelTarget};
+use replay::{ReplayPlayback, ReplayPlugin, ReplayRecorder};
 use resources::WindowScale;
+use rollback::RollbackFramePlugin;
+use save::Campaign;
+use settings::{
+    apply_master_volume, apply_window_mode, apply_window_scale, remember_window_size, Settings,
+};
+use states::create_game_menu::CreateGameMenuPlugin;
 use states::full_lobby::FullLobbyPlugin;
+use states::local_lobby::{LocalLobbyPlugin, LocalPlayerCount};
 use states::main_menu::MainMenuPlugin;
-use states::waiting_lobby::{CMRole, GameConfig, RoomID, WaitingLobbyPlugin};
+use states::past_games_menu::PastGamesMenuPlugin;
+use states::settings_menu::SettingsMenuPlugin;
+use states::waiting_lobby::{CMRole, GameConfig, PartnerName, RoomID, WaitingLobbyPlugin};
 
+mod animation;
 mod args;
+mod audio;
+mod camera;
 mod components;
+mod effects;
+mod history;
+mod hud;
 mod input;
 mod interactions;
 mod level;
 mod movement;
 mod physics;
+mod render;
+mod replay;
 mod resources;
+mod rollback;
+mod save;
+mod settings;
 mod states;
+mod storage;
 
-type Config = bevy_ggrs::GgrsConfig<u8, PeerId>;
+type Config = bevy_ggrs::GgrsConfig<input::PlayerInput, PeerId>;
 
 pub const MIN_WINDOW_SIZE: f32 = 196.0;
 
@@ -35,6 +70,8 @@ fn main() {
     let args = Args::parse();
     info!("{args:?}");
 
+    let settings = Settings::load();
+
     App::new()
         .add_plugins((
             DefaultPlugins
@@ -42,12 +79,17 @@ fn main() {
                     primary_window: Some(Window {
                         fit_canvas_to_parent: true,
                         prevent_default_event_handling: false,
-                        resizable: false,
-                        resolution: WindowResolution::new(MIN_WINDOW_SIZE, MIN_WINDOW_SIZE),
-                        mode: bevy::window::WindowMode::Windowed,
+                        resizable: true,
+                        resolution: settings.window_resolution(),
+                        resize_constraints: WindowResizeConstraints {
+                            min_width: MIN_WINDOW_SIZE,
+                            min_height: MIN_WINDOW_SIZE,
+                            ..default()
+                        },
+                        mode: settings.window_mode(),
                         enabled_buttons: EnabledButtons {
                             minimize: true,
-                            maximize: false,
+                            maximize: true,
                             close: true,
                         },
                         ..default()
@@ -57,20 +99,65 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
             GgrsPlugin::<Config>::default(),
             MainMenuPlugin,
+            SettingsMenuPlugin,
+            PastGamesMenuPlugin,
+            CreateGameMenuPlugin,
             WaitingLobbyPlugin,
+            LocalLobbyPlugin,
             FullLobbyPlugin,
         ))
         .init_state::<AppState>()
-        .add_plugins(PhysicsPlugin)
+        .add_plugins((
+            PhysicsPlugin,
+            CameraPlugin,
+            AnimationPlugin,
+            PixelPerfectPlugin,
+            RollbackFramePlugin,
+            SoundPlugin,
+            EffectsPlugin,
+            HudPlugin,
+            ReplayPlugin,
+        ))
         .insert_resource(args)
-        .insert_resource(WindowScale::new())
+        .insert_resource(WindowScale::new(settings.window_scale))
+        .insert_resource(settings)
+        .init_resource::<ConnectedGamepads>()
+        .init_resource::<GamepadMenuInput>()
+        .insert_resource(InputMap::load())
         .insert_resource(ClearColor(Color::srgb(0.53, 0.53, 0.53)))
-        .add_systems(Startup, setup)
-        .add_systems(Update, handle_window_resize)
-        .add_systems(ReadInputs, input::read_local_inputs)
+        .add_systems(Startup, (setup_pixel_target, setup).chain())
+        .add_systems(
+            Update,
+            (
+                toggle_fullscreen,
+                handle_window_resize,
+                apply_window_scale.run_if(resource_changed::<WindowScale>),
+                apply_window_mode.run_if(resource_changed::<Settings>),
+                apply_master_volume.run_if(resource_changed::<Settings>),
+                remember_window_size,
+            )
+                .chain(),
+        )
+        .add_systems(
+            PreUpdate,
+            (handle_gamepad_connections, read_gamepad_menu_input)
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            ReadInputs,
+            input::read_local_inputs.run_if(not(resource_exists::<ReplayPlayback>)),
+        )
         .rollback_component_with_clone::<Transform>()
         .rollback_component_with_copy::<Player>()
         .rollback_component_with_clone::<CoyoteTime>()
+        .rollback_component_with_clone::<JumpBuffer>()
+        .rollback_component_with_clone::<MovingPlatform>()
+        .rollback_component_with_copy::<Door>()
+        .rollback_component_with_copy::<MovementParams>()
+        .rollback_component_with_copy::<Jumping>()
+        .rollback_component_with_copy::<InputHistory>()
+        .rollback_component_with_copy::<Climbing>()
         .init_ggrs_state::<MultiplayerGameState>()
         .add_systems(
             OnExit(AppState::FullLobby),
@@ -81,7 +168,6 @@ fn main() {
             )
                 .chain(),
         )
-        .add_systems(OnEnter(AppState::CreateGameMenu), add_bearer_role)
         .add_systems(OnEnter(AppState::JoinGameMenu), add_receive_role)
         .insert_resource(RoomID(20))
         .run();
@@ -99,6 +185,7 @@ pub enum AppState {
     CreateGameMenu,
     JoinGameMenu,
     WaitingInLobby,
+    LocalLobby,
     FullLobby,
 }
 
@@ -113,6 +200,11 @@ fn remove_multiplayer_resources(mut commands: Commands) {
     commands.close_socket::<MultipleChannels>();
     commands.remove_resource::<GameConfig>();
     commands.remove_resource::<CMRole>();
+    commands.remove_resource::<LocalPlayerCount>();
+    commands.remove_resource::<PartnerName>();
+    commands.remove_resource::<Campaign>();
+    commands.remove_resource::<ReplayRecorder>();
+    commands.remove_resource::<ReplayPlayback>();
     commands.remove_resource::<bevy_ggrs::Session<Config>>();
     info!("Removed multiplayer resources");
 }
@@ -138,29 +230,35 @@ fn add_receive_role(mut commands: Commands, mut next_state: ResMut<NextState<App
     next_state.set(AppState::WaitingInLobby);
 }
 
-fn add_bearer_role(mut commands: Commands, mut next_state: ResMut<NextState<AppState>>) {
-    commands.insert_resource::<CMRole>(CMRole::ConfigBearer(GameConfig {
-        seed: 10,
-        difficulty: 1,
-    }));
-    next_state.set(AppState::WaitingInLobby);
-}
-
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, pixel_target: Res<PixelTarget>) {
     let mut camera_bundle = Camera2dBundle::default();
+    camera_bundle.camera.target = pixel_target.render_target();
     camera_bundle.projection.scaling_mode = ScalingMode::Fixed {
         width: 10.,
         height: 10.,
     };
-    commands.spawn(camera_bundle);
+    commands.spawn((camera_bundle.clone(), MainCamera, IsDefaultUiCamera));
+
+    // the right half of the screen when split screen kicks in
+    camera_bundle.camera = Camera {
+        order: 1,
+        is_active: false,
+        target: pixel_target.render_target(),
+        ..default()
+    };
+    commands.spawn((camera_bundle, SplitCamera));
     let id = commands.register_one_shot_system(despawn_all_but_camera);
     commands.insert_resource(DespawnAllButCameraID(id));
 }
 
-pub fn despawn_all_but_camera(
-    mut commands: Commands,
-    query: Query<Entity, (Without<Camera>, Without<Window>)>,
-) {
+/// kept alive when a state clears the world with `despawn_all_but_camera`
+#[derive(Component, Debug)]
+pub struct Persistent;
+
+/// everything a state clears away when it sets up its own scene
+type Despawnable = (Without<Camera>, Without<Window>, Without<Persistent>);
+
+pub fn despawn_all_but_camera(mut commands: Commands, query: Query<Entity, Despawnable>) {
     for entity in &query {
         if let Some(e) = commands.get_entity(entity) {
             e.despawn_recursive();
@@ -1,49 +1,98 @@
 use bevy::prelude::*;
-use bevy_ggrs::PlayerInputs;
 
 use crate::{
-    components::{CoyoteTime, Player},
-    input,
-    physics::Velocity,
-    Config,
+    audio::{RollbackSfx, Sfx},
+    components::{
+        CoyoteTime, InputHistory, JumpBuffer, Jumping, MovementParams, MovingPlatform, Player,
+    },
+    effects::{Effect, RollbackVfx},
+    input::{self, INPUT_JUMP},
+    physics::{ground_material, Collider, CollidingSide, PhysicsMaterial, Velocity},
 };
 
+/// a player's input, jump timers and what they're standing on
+type MovingPlayerQuery<'a> = (
+    &'a Player,
+    &'a mut Velocity,
+    &'a mut CoyoteTime,
+    &'a mut JumpBuffer,
+    &'a mut Jumping,
+    &'a InputHistory,
+    &'a MovementParams,
+    &'a Collider,
+    &'a Transform,
+);
+
 pub fn move_player_multiplayer(
-    mut players: Query<(&mut Velocity, &mut CoyoteTime, &Player, &mut Sprite)>,
-    inputs: Res<PlayerInputs<Config>>,
+    mut players: Query<MovingPlayerQuery>,
+    materials: Query<&PhysicsMaterial>,
+    time: Res<Time>,
+    mut sfx: RollbackSfx,
+    mut vfx: RollbackVfx,
 ) {
-    for (mut velocity, mut ct, player, mut sprite) in &mut players {
-        let input = inputs.get(player.handle);
+    let delta = time.delta_seconds();
 
-        if input.is_none() {
-            continue;
+    for (player, mut velocity, mut ct, mut jb, mut jumping, history, params, collider, transform) in
+        &mut players
+    {
+        let direction = input::direction(history.current());
+
+        let grounded = collider.check_colliding_solid_side(CollidingSide::Bottom);
+
+        if collider.just_landed() {
+            let feet = transform.translation.truncate() - Vec2::new(0., collider.half_size().y);
+
+            sfx.play(Sfx::Land, player.handle);
+            vfx.spawn(Effect::Dust, player.handle, feet);
         }
+        let material = ground_material(collider, &materials);
+        let control = if grounded { 1. } else { params.air_control };
 
-        let (input, _) = *input.unwrap();
+        let target = direction.x * params.max_speed;
+        let speeding_up = target != 0.
+            && (velocity.0.x == 0. || velocity.0.x.signum() == target.signum())
+            && target.abs() > velocity.0.x.abs();
 
-        let direction = input::direction(input);
+        let rate = match speeding_up {
+            true => params.acceleration * material.acceleration,
+            false => params.deceleration * material.friction,
+        } * control;
 
-        velocity.0.x = direction.x;
+        velocity.0.x = move_towards(velocity.0.x, target, rate * delta);
 
-        if direction.x < 0. {
-            sprite.flip_x = true;
-        } else if direction.x > 0. {
-            sprite.flip_x = false;
-        }
+        // applied before gravity, so the fastest fall is one frame of gravity above this
+        velocity.0.y = velocity.0.y.max(-params.max_fall_speed);
 
-        if direction.y <= 0. {
-            continue;
-        }
+        jb.update(history.just_pressed(INPUT_JUMP), time.delta());
 
-        if ct.get() {
-            velocity.0.y = direction.y;
+        if jb.get() && ct.get() {
+            velocity.0.y = params.jump_impulse;
             ct.clear();
+            jb.clear();
+            jumping.0 = true;
+            sfx.play(Sfx::Jump, player.handle);
             continue;
         }
+
+        if jumping.0 && (direction.y <= 0. || velocity.0.y <= 0.) {
+            if velocity.0.y > 0. {
+                velocity.0.y *= params.jump_cut;
+            }
+
+            jumping.0 = false;
+        }
     }
 }
 
-pub fn reset(mut objects: Query<(&mut Transform, &mut Velocity)>) {
+fn move_towards(current: f32, target: f32, max_delta: f32) -> f32 {
+    if (target - current).abs() <= max_delta {
+        target
+    } else {
+        current + (target - current).signum() * max_delta
+    }
+}
+
+pub fn reset(mut objects: Query<(&mut Transform, &mut Velocity), Without<MovingPlatform>>) {
     for (mut t, mut v) in &mut objects {
         if t.translation.y < -5. {
             t.translation = Vec3::new(0., 3., 0.);
@@ -51,3 +100,100 @@ pub fn reset(mut objects: Query<(&mut Transform, &mut Velocity)>) {
         }
     }
 }
+
+pub fn move_platforms(
+    time: Res<Time>,
+    mut platforms: Query<(&mut MovingPlatform, &Transform, &mut Velocity)>,
+) {
+    let delta = time.delta_seconds();
+
+    if delta <= 0. {
+        return;
+    }
+
+    for (mut platform, transform, mut velocity) in &mut platforms {
+        platform.tick(time.delta());
+        velocity.0 = (platform.position() - transform.translation.xy()) / delta;
+    }
+}
+
+pub fn carry_riders(
+    time: Res<Time>,
+    mut riders: Query<(Entity, &mut Transform, &Collider), With<Player>>,
+    platforms: Query<&Velocity, With<MovingPlatform>>,
+    players: Query<&Velocity, With<Player>>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut transform, collider) in &mut riders {
+        let below = collider.get_all_colliding_side(CollidingSide::Bottom);
+
+        let carried_by = below
+            .iter()
+            .find_map(|e| platforms.get(*e).ok().map(|v| v.0))
+            // players standing on another player get dragged along sideways, the solver already
+            // keeps them on top when the one below jumps or falls
+            .or_else(|| {
+                below
+                    .iter()
+                    .filter(|e| **e != entity)
+                    .find_map(|e| players.get(*e).ok().map(|v| Vec2::new(v.0.x, 0.)))
+            });
+
+        if let Some(velocity) = carried_by {
+            transform.translation += (velocity * delta).extend(0.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::components::PlatformPath;
+
+    #[test]
+    fn riders_move_with_their_platform() {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(0.5));
+        world.insert_resource(time);
+
+        let platform = world
+            .spawn((
+                MovingPlatform::new(PlatformPath::Waypoints {
+                    points: vec![Vec2::ZERO, Vec2::new(4., 0.)],
+                    speed: 2.,
+                    looping: false,
+                }),
+                Transform::default(),
+                Velocity::default(),
+            ))
+            .id();
+
+        let mut collider = Collider::new(Vec2::ONE);
+        collider.add_collision(platform, CollidingSide::Bottom, 0., true);
+        let rider = world
+            .spawn((
+                Player { handle: 0 },
+                Transform::from_xyz(0., 1., 0.),
+                collider,
+            ))
+            .id();
+
+        world.run_system_once(move_platforms);
+        world.run_system_once(carry_riders);
+
+        assert_eq!(
+            world.get::<Velocity>(platform).unwrap().0,
+            Vec2::new(2., 0.)
+        );
+        assert_eq!(
+            world.get::<Transform>(rider).unwrap().translation,
+            Vec3::new(1., 1., 0.)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::{prelude::*, utils::HashSet};
+use bevy::prelude::*;
 use bevy_ggrs::{GgrsApp, GgrsSchedule};
 use bevy_roll_safe::{apply_state_transition, run_enter_schedule};
 use enum_ordinalize::Ordinalize;
@@ -16,6 +16,7 @@ impl Plugin for PhysicsPlugin {
                 handle_velocity,
                 handle_colliders,
                 handle_solids,
+                handle_bounces,
             )
                 .chain()
                 .in_set(PhysicsSet)
@@ -29,6 +30,7 @@ impl Plugin for PhysicsPlugin {
                 handle_velocity,
                 handle_colliders,
                 handle_solids,
+                handle_bounces,
             )
                 .chain()
                 .in_set(PhysicsSet),
@@ -96,10 +98,51 @@ impl CollidingSide {
     }
 }
 
+pub const LAYER_PLAYER: u8 = 1 << 0;
+pub const LAYER_WORLD: u8 = 1 << 1;
+pub const LAYER_VINE: u8 = 1 << 2;
+pub const LAYER_TRIGGER: u8 = 1 << 3;
+pub const LAYER_ALL: u8 = u8::MAX;
+
+/// how far above a slope a falling or walking body still counts as standing on it, so walking
+/// downhill doesn't turn into a series of tiny falls
+const SLOPE_SNAP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlopeAngle {
+    Deg45,
+    Deg22_5,
+}
+
+impl SlopeAngle {
+    pub fn tan(&self) -> f32 {
+        match self {
+            SlopeAngle::Deg45 => 1.,
+            // tan(22.5°), written out so every peer builds identical slopes
+            SlopeAngle::Deg22_5 => std::f32::consts::SQRT_2 - 1.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Rect,
+    /// right triangle filling the bounding box, its surface rising towards the right or left
+    Slope {
+        rises_right: bool,
+    },
+    Circle,
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct Collider {
     bounding_box: Vec2,
+    shape: ColliderShape,
+    layer: u8,
+    mask: u8,
+    trigger: bool,
     collisions: Vec<(Entity, CollidingSide, f32, bool)>,
+    previous_collisions: Vec<Entity>,
     colliding_side: u8,
 }
 
@@ -110,11 +153,63 @@ impl Collider {
     pub fn new(bounding_box: Vec2) -> Self {
         Self {
             bounding_box: bounding_box.abs(),
+            shape: ColliderShape::Rect,
+            layer: LAYER_ALL,
+            mask: LAYER_ALL,
+            trigger: false,
             collisions: Vec::new(),
+            previous_collisions: Vec::new(),
             colliding_side: 0,
         }
     }
 
+    /// non-solid volume on `LAYER_TRIGGER` that only reports which players entered and left it,
+    /// overlapping one never counts as touching a side so it can't hold anything up
+    pub fn trigger(bounding_box: Vec2) -> Self {
+        Self {
+            trigger: true,
+            ..Self::new(bounding_box).with_layers(LAYER_TRIGGER, LAYER_PLAYER)
+        }
+    }
+
+    pub fn is_trigger(&self) -> bool {
+        self.trigger
+    }
+
+    pub fn slope(width: f32, angle: SlopeAngle, rises_right: bool) -> Self {
+        Self {
+            shape: ColliderShape::Slope { rises_right },
+            ..Self::new(Vec2::new(width, width * angle.tan()))
+        }
+    }
+
+    pub fn circle(radius: f32) -> Self {
+        Self {
+            shape: ColliderShape::Circle,
+            ..Self::new(Vec2::splat(radius * 2.))
+        }
+    }
+
+    /// puts the collider on the `layer` bits and only lets it collide with colliders on the
+    /// `mask` bits, a pair is only tested when both of them accept each other
+    pub fn with_layers(mut self, layer: u8, mask: u8) -> Self {
+        self.layer = layer;
+        self.mask = mask;
+        self
+    }
+
+    pub fn set_mask(&mut self, mask: u8) {
+        self.mask = mask;
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.bounding_box / 2.
+    }
+
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.mask & other.layer != 0 && other.mask & self.layer != 0
+    }
+
     pub fn check_colliding_side(&self, side: CollidingSide) -> bool {
         self.colliding_side & (1u8 << side.ordinal()) > 0
     }
@@ -147,11 +242,57 @@ impl Collider {
         self.colliding_side |= 1u8 << colliding_side.ordinal();
     }
 
+    /// records a contact with a trigger without marking the side as touched
+    pub fn add_trigger_contact(
+        &mut self,
+        entity: Entity,
+        colliding_side: CollidingSide,
+        overlap: f32,
+    ) {
+        self.collisions
+            .push((entity, colliding_side, overlap, false));
+    }
+
     pub fn clear_collisions(&mut self) {
+        self.previous_collisions.clear();
+        self.previous_collisions
+            .extend(self.collisions.iter().map(|(e, _, _, _)| *e));
         self.colliding_side = 0;
         self.collisions.clear();
     }
 
+    /// entities this collider touches this frame but didn't touch last frame
+    pub fn started_colliding(&self) -> Vec<Entity> {
+        self.colliding_entities()
+            .filter(|e| !self.previous_collisions.contains(e))
+            .collect()
+    }
+
+    /// standing on something solid after touching none of it last frame, walking from one piece
+    /// of ground onto the next doesn't count
+    pub fn just_landed(&self) -> bool {
+        let mut ground = self
+            .collisions
+            .iter()
+            .filter(|(_, side, _, solid)| *side == CollidingSide::Bottom && *solid)
+            .peekable();
+
+        ground.peek().is_some() && ground.all(|(e, _, _, _)| !self.previous_collisions.contains(e))
+    }
+
+    /// entities this collider touched last frame but doesn't touch anymore
+    pub fn stopped_colliding(&self) -> Vec<Entity> {
+        self.previous_collisions
+            .iter()
+            .filter(|e| self.colliding_with(e).is_none())
+            .copied()
+            .collect()
+    }
+
+    pub fn colliding_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.collisions.iter().map(|(e, _, _, _)| *e)
+    }
+
     pub fn colliding_with(&self, entity: &Entity) -> Option<(CollidingSide, f32, bool)> {
         self.collisions
             .iter()
@@ -189,92 +330,651 @@ pub fn handle_gravity(
     }
 }
 
-pub fn handle_solids(mut objects: Query<(Entity, &mut Transform, &Collider, &Solid)>) {
-    let mut handled_collisions: HashSet<(Entity, Entity)> = HashSet::new();
-    let mut iter = objects.iter_combinations_mut();
+/// how many times overlapping solids get pushed apart per frame, stacks and pushes through
+/// several bodies need a few passes to settle
+const SOLVER_ITERATIONS: usize = 4;
+
+/// everything the solver reads or moves, bodies without a `Mass` weigh 1
+type SolidQuery<'a> = (
+    Entity,
+    &'a mut Transform,
+    &'a Collider,
+    &'a Solid,
+    Option<&'a Mass>,
+    Option<&'a Velocity>,
+);
+
+pub fn handle_solids(mut objects: Query<SolidQuery>) {
+    let mut bodies: Vec<(Entity, SolverBody)> = objects
+        .iter()
+        .map(|(entity, transform, collider, solid, mass, velocity)| {
+            (
+                entity,
+                SolverBody {
+                    position: transform.translation.xy(),
+                    collider,
+                    inverse_mass: match solid.0 {
+                        true => 1. / mass.map_or(1., |m| m.0).max(f32::EPSILON),
+                        false => 0.,
+                    },
+                    snap: velocity.is_some_and(|v| v.0.y <= 0.),
+                },
+            )
+        })
+        .collect();
 
-    while let Some([(e1, mut t1, c1, s1), (e2, mut t2, c2, s2)]) = iter.fetch_next() {
-        let collision = c1.colliding_with(&e2);
+    // query order isn't guaranteed to match between peers, entity order is
+    bodies.sort_by_key(|(entity, _)| *entity);
 
-        if collision.is_none() {
-            continue;
+    let (entities, mut solver_bodies): (Vec<Entity>, Vec<SolverBody>) = bodies.into_iter().unzip();
+
+    solve(&mut solver_bodies, SOLVER_ITERATIONS);
+
+    let positions: Vec<Vec2> = solver_bodies.iter().map(|body| body.position).collect();
+
+    for (entity, position) in entities.into_iter().zip(positions) {
+        if let Ok((_, mut transform, ..)) = objects.get_mut(entity) {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
         }
+    }
+}
 
-        if handled_collisions.contains(&(e1, e2)) {
+/// slowest landing that still bounces off a surface with restitution, so resting bodies don't
+/// jitter
+const BOUNCE_THRESHOLD: f32 = 1.;
+
+/// surface properties of a platform, used by whatever is standing on it
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsMaterial {
+    /// scales how quickly bodies slow down or turn around, values near 0 slide like ice
+    pub friction: f32,
+    /// fraction of the falling speed bounced back on landing
+    pub restitution: f32,
+    /// scales how quickly bodies speed up
+    pub acceleration: f32,
+}
+
+impl PhysicsMaterial {
+    pub const ICE: Self = Self {
+        friction: 0.05,
+        restitution: 0.,
+        acceleration: 0.3,
+    };
+
+    pub const BOUNCY: Self = Self {
+        friction: 1.,
+        restitution: 0.8,
+        acceleration: 1.,
+    };
+
+    pub const MUD: Self = Self {
+        friction: 1.,
+        restitution: 0.,
+        acceleration: 0.25,
+    };
+}
+
+impl Default for PhysicsMaterial {
+    fn default() -> Self {
+        Self {
+            friction: 1.,
+            restitution: 0.,
+            acceleration: 1.,
+        }
+    }
+}
+
+/// material of whatever the collider is standing on, the default material when it's in the air or
+/// on something without one
+pub fn ground_material(
+    collider: &Collider,
+    materials: &Query<&PhysicsMaterial>,
+) -> PhysicsMaterial {
+    collider
+        .get_all_colliding_side(CollidingSide::Bottom)
+        .into_iter()
+        .find_map(|entity| materials.get(entity).ok().copied())
+        .unwrap_or_default()
+}
+
+pub fn handle_bounces(
+    mut objects: Query<(&Collider, &mut Velocity), With<Solid>>,
+    materials: Query<&PhysicsMaterial>,
+) {
+    for (collider, mut velocity) in &mut objects {
+        if velocity.0.y > -BOUNCE_THRESHOLD {
             continue;
         }
 
-        let (side, overlap, _) = collision.unwrap();
+        let material = ground_material(collider, &materials);
 
-        let movement = match side {
-            CollidingSide::Top => Vec2::new(0., -overlap),
-            CollidingSide::Bottom => Vec2::new(0., overlap),
-            CollidingSide::Left => Vec2::new(overlap, 0.),
-            CollidingSide::Right => Vec2::new(-overlap, 0.),
+        if material.restitution > 0. {
+            velocity.0.y = -velocity.0.y * material.restitution;
         }
-        .extend(0.);
+    }
+}
 
-        match (
-            s1.0,
-            s2.0,
-            c1.check_colliding_side(side.opposite()),
-            c2.check_colliding_side(side),
-        ) {
-            (true, true, false, false) => {
-                t2.translation -= movement / 2.;
-                t1.translation += movement / 2.;
-            }
-            (true, false, _, _) | (true, true, false, true) => {
-                t1.translation += movement;
-            }
-            (false, true, _, _) | (true, true, true, false) => {
-                t2.translation -= movement;
+/// how heavy a solid body is when it gets pushed by other solid bodies, defaults to 1
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Mass(pub f32);
+
+#[derive(Debug, Clone)]
+pub struct SolverBody<'a> {
+    pub position: Vec2,
+    pub collider: &'a Collider,
+    /// 0 for bodies that never move, like platforms
+    pub inverse_mass: f32,
+    pub snap: bool,
+}
+
+/// pushes overlapping bodies apart
+///
+/// a body resting on top of another always takes the whole correction so stacks stay put instead
+/// of pressing the body underneath into the floor, sideways overlaps are split by mass so walking
+/// into another player pushes them along
+pub fn solve(bodies: &mut [SolverBody<'_>], iterations: usize) {
+    // sides of each body that are pressed against something that can't move, pushing a body
+    // further into those has to move the pusher instead
+    let mut blocked = vec![0u8; bodies.len()];
+
+    for _ in 0..iterations {
+        for i in 0..bodies.len() {
+            for j in (i + 1)..bodies.len() {
+                let (head, tail) = bodies.split_at_mut(j);
+                let (a, b) = (&mut head[i], &mut tail[0]);
+
+                if a.inverse_mass + b.inverse_mass <= 0.
+                    || a.collider.is_trigger()
+                    || b.collider.is_trigger()
+                    || !a.collider.interacts_with(b.collider)
+                {
+                    continue;
+                }
+
+                let Some((side, overlap)) = intersect(
+                    a.collider, a.position, a.snap, b.collider, b.position, b.snap,
+                ) else {
+                    continue;
+                };
+
+                // only slopes report negative overlaps, and only to pull a body down onto them
+                if overlap < 0. && b.inverse_mass > 0. && a.inverse_mass > 0. {
+                    continue;
+                }
+
+                let movement = match side {
+                    CollidingSide::Top => Vec2::new(0., -overlap),
+                    CollidingSide::Bottom => Vec2::new(0., overlap),
+                    CollidingSide::Left => Vec2::new(overlap, 0.),
+                    CollidingSide::Right => Vec2::new(-overlap, 0.),
+                };
+
+                let a_side = 1u8 << side.ordinal();
+                let b_side = 1u8 << side.opposite().ordinal();
+
+                let share = match (side, a.inverse_mass > 0., b.inverse_mass > 0.) {
+                    (_, true, false) => 1.,
+                    (_, false, true) => 0.,
+                    (CollidingSide::Bottom, _, _) => 1.,
+                    (CollidingSide::Top, _, _) => 0.,
+                    _ if blocked[j] & a_side > 0 => 1.,
+                    _ if blocked[i] & b_side > 0 => 0.,
+                    _ => a.inverse_mass / (a.inverse_mass + b.inverse_mass),
+                };
+
+                if share >= 1. && (b.inverse_mass <= 0. || blocked[j] & a_side > 0) {
+                    blocked[i] |= a_side;
+                }
+
+                if share <= 0. && (a.inverse_mass <= 0. || blocked[i] & b_side > 0) {
+                    blocked[j] |= b_side;
+                }
+
+                a.position += movement * share;
+                b.position -= movement * (1. - share);
             }
-            _ => (),
         }
-
-        handled_collisions.insert((e1, e2));
-        handled_collisions.insert((e2, e1));
     }
 }
 
 pub fn handle_colliders(
-    mut objects: Query<(Entity, &Transform, &mut Collider)>,
+    mut objects: Query<(Entity, &Transform, &mut Collider, Option<&Velocity>)>,
     solids: Query<&Solid>,
 ) {
-    for (_, _, mut collider) in &mut objects {
+    for (_, _, mut collider, _) in &mut objects {
         collider.clear_collisions();
     }
 
     let mut iter = objects.iter_combinations_mut();
 
-    while let Some([(e1, t1, mut c1), (e2, t2, mut c2)]) = iter.fetch_next() {
-        let diff = t1.translation.xy() - t2.translation.xy();
-        let edge_distance = diff.abs() - (c1.bounding_box + c2.bounding_box) / 2.;
+    while let Some([(e1, t1, mut c1, v1), (e2, t2, mut c2, v2)]) = iter.fetch_next() {
+        if !c1.interacts_with(&c2) {
+            continue;
+        }
+
+        let falling = |v: Option<&Velocity>| v.is_some_and(|v| v.0.y <= 0.);
 
-        if edge_distance.max_element() > 0. {
+        let Some((side, overlap)) = intersect(
+            &c1,
+            t1.translation.xy(),
+            falling(v1),
+            &c2,
+            t2.translation.xy(),
+            falling(v2),
+        ) else {
             continue;
+        };
+
+        if c1.is_trigger() || c2.is_trigger() {
+            c1.add_trigger_contact(e2, side, overlap);
+            c2.add_trigger_contact(e1, side.opposite(), overlap);
+            continue;
+        }
+
+        let solid = solids.contains(e1) && solids.contains(e2);
+
+        c1.add_collision(e2, side, overlap, solid);
+        c2.add_collision(e1, side.opposite(), overlap, solid);
+    }
+}
+
+/// tests two colliders against each other, returning the side of `a` that is hit and how far the
+/// two overlap along that side's axis
+///
+/// `snap_a` and `snap_b` let a body that isn't moving upwards stick to slopes it is hovering just
+/// above, which reports a negative overlap that pulls it back down onto the surface
+pub fn intersect(
+    a: &Collider,
+    pa: Vec2,
+    snap_a: bool,
+    b: &Collider,
+    pb: Vec2,
+    snap_b: bool,
+) -> Option<(CollidingSide, f32)> {
+    match (a.shape, b.shape) {
+        (ColliderShape::Slope { .. }, ColliderShape::Slope { .. }) => {
+            intersect_rects(a.bounding_box, pa, b.bounding_box, pb)
         }
+        (_, ColliderShape::Slope { rises_right }) => {
+            intersect_slope(a, pa, snap_a, b.bounding_box, pb, rises_right)
+        }
+        (ColliderShape::Slope { rises_right }, _) => {
+            intersect_slope(b, pb, snap_b, a.bounding_box, pa, rises_right)
+                .map(|(side, overlap)| (side.opposite(), overlap))
+        }
+        (ColliderShape::Circle, ColliderShape::Circle) => {
+            let offset = pa - pb;
+            let overlap = (a.bounding_box.x + b.bounding_box.x) / 2. - offset.length();
 
-        let collisions = if edge_distance.x > edge_distance.y {
-            if diff.x < 0. {
-                (CollidingSide::Right, CollidingSide::Left)
-            } else {
-                (CollidingSide::Left, CollidingSide::Right)
+            if overlap < 0. {
+                return None;
             }
+
+            Some((side_facing(-offset), overlap))
+        }
+        (ColliderShape::Circle, ColliderShape::Rect) => {
+            intersect_circle_rect(a.bounding_box.x / 2., pa, b.bounding_box, pb)
+        }
+        (ColliderShape::Rect, ColliderShape::Circle) => {
+            intersect_circle_rect(b.bounding_box.x / 2., pb, a.bounding_box, pa)
+                .map(|(side, overlap)| (side.opposite(), overlap))
+        }
+        (ColliderShape::Rect, ColliderShape::Rect) => {
+            intersect_rects(a.bounding_box, pa, b.bounding_box, pb)
+        }
+    }
+}
+
+/// side of a body that faces along `direction`
+fn side_facing(direction: Vec2) -> CollidingSide {
+    if direction.x.abs() > direction.y.abs() {
+        if direction.x > 0. {
+            CollidingSide::Right
+        } else {
+            CollidingSide::Left
+        }
+    } else if direction.y > 0. {
+        CollidingSide::Top
+    } else {
+        CollidingSide::Bottom
+    }
+}
+
+fn intersect_rects(a_box: Vec2, pa: Vec2, b_box: Vec2, pb: Vec2) -> Option<(CollidingSide, f32)> {
+    let diff = pa - pb;
+    let edge_distance = diff.abs() - (a_box + b_box) / 2.;
+
+    if edge_distance.max_element() > 0. {
+        return None;
+    }
+
+    let side = if edge_distance.x > edge_distance.y {
+        if diff.x < 0. {
+            CollidingSide::Right
+        } else {
+            CollidingSide::Left
+        }
+    } else {
+        if diff.y < 0. {
+            CollidingSide::Top
         } else {
-            if diff.y < 0. {
-                (CollidingSide::Top, CollidingSide::Bottom)
-            } else {
-                (CollidingSide::Bottom, CollidingSide::Top)
+            CollidingSide::Bottom
+        }
+    };
+
+    Some((side, edge_distance.max_element().abs()))
+}
+
+fn intersect_circle_rect(
+    radius: f32,
+    center: Vec2,
+    rect: Vec2,
+    rect_pos: Vec2,
+) -> Option<(CollidingSide, f32)> {
+    let half = rect / 2.;
+    let closest = center.clamp(rect_pos - half, rect_pos + half);
+    let offset = center - closest;
+
+    // the center is inside the rect, so the box test gives the way out
+    if offset == Vec2::ZERO {
+        return intersect_rects(Vec2::splat(radius * 2.), center, rect, rect_pos);
+    }
+
+    if offset.length() > radius {
+        return None;
+    }
+
+    let side = side_facing(-offset);
+
+    let overlap = match side {
+        CollidingSide::Left | CollidingSide::Right => radius - offset.x.abs(),
+        CollidingSide::Top | CollidingSide::Bottom => radius - offset.y.abs(),
+    };
+
+    Some((side, overlap))
+}
+
+/// tests a body against a slope, only the hypotenuse is sloped, the floor and tall wall of the
+/// triangle behave like a box
+fn intersect_slope(
+    body: &Collider,
+    pos: Vec2,
+    snap: bool,
+    slope: Vec2,
+    slope_pos: Vec2,
+    rises_right: bool,
+) -> Option<(CollidingSide, f32)> {
+    let diff = pos - slope_pos;
+    let edge_distance = diff.abs() - (body.bounding_box + slope) / 2.;
+    let tolerance = if snap { SLOPE_SNAP } else { 0. };
+
+    if edge_distance.x > 0. || edge_distance.y > tolerance {
+        return None;
+    }
+
+    let left = slope_pos.x - slope.x / 2.;
+    let right = slope_pos.x + slope.x / 2.;
+    let bottom = slope_pos.y - slope.y / 2.;
+    let half = body.bounding_box / 2.;
+    let rise = slope.y / slope.x;
+
+    let beside_wall = if rises_right {
+        pos.x > right
+    } else {
+        pos.x < left
+    };
+
+    if beside_wall || pos.y < bottom {
+        return intersect_rects(body.bounding_box, pos, slope, slope_pos);
+    }
+
+    // the lowest point of the body that can touch the surface, for boxes that's the uphill
+    // bottom corner, circles rest on the surface further out from their center
+    let (sample_x, body_bottom) = match body.shape {
+        ColliderShape::Circle => (pos.x, pos.y - half.x * (1. + rise * rise).sqrt()),
+        _ if rises_right => (pos.x + half.x, pos.y - half.y),
+        _ => (pos.x - half.x, pos.y - half.y),
+    };
+
+    let sample_x = sample_x.clamp(left, right);
+
+    let surface = if rises_right {
+        bottom + (sample_x - left) * rise
+    } else {
+        bottom + (right - sample_x) * rise
+    };
+
+    let overlap = surface - body_bottom;
+
+    if overlap < -tolerance || overlap > half.y * 2. {
+        return None;
+    }
+
+    Some((CollidingSide::Bottom, overlap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER_SIZE: Vec2 = Vec2::new(1., 1.);
+    // roughly one frame of falling at full gravity
+    const FALL_STEP: f32 = 0.05;
+
+    /// test bodies borrow their colliders for the whole test, same as they would from the query
+    fn leak(collider: Collider) -> &'static Collider {
+        Box::leak(Box::new(collider))
+    }
+
+    fn floor() -> SolverBody<'static> {
+        SolverBody {
+            position: Vec2::new(0., -0.5),
+            collider: leak(Collider::new(Vec2::new(20., 1.))),
+            inverse_mass: 0.,
+            snap: false,
+        }
+    }
+
+    fn wall(x: f32) -> SolverBody<'static> {
+        SolverBody {
+            position: Vec2::new(x, 5.),
+            collider: leak(Collider::new(Vec2::new(1., 10.))),
+            inverse_mass: 0.,
+            snap: false,
+        }
+    }
+
+    fn player(x: f32, y: f32) -> SolverBody<'static> {
+        SolverBody {
+            position: Vec2::new(x, y),
+            collider: leak(Collider::new(PLAYER_SIZE)),
+            inverse_mass: 1.,
+            snap: true,
+        }
+    }
+
+    #[test]
+    fn stacked_players_stay_still() {
+        let mut bodies = vec![floor(), player(0., 0.5), player(0., 1.5)];
+
+        for _ in 0..120 {
+            bodies[1].position.y -= FALL_STEP;
+            bodies[2].position.y -= FALL_STEP;
+            solve(&mut bodies, SOLVER_ITERATIONS);
+        }
+
+        assert!((bodies[1].position.y - 0.5).abs() < 1e-4);
+        assert!((bodies[2].position.y - 1.5).abs() < 1e-4);
+        assert_eq!(bodies[1].position.x, 0.);
+        assert_eq!(bodies[2].position.x, 0.);
+    }
+
+    #[test]
+    fn landing_on_a_player_does_not_push_them_into_the_floor() {
+        let mut bodies = vec![floor(), player(0., 0.5), player(0.2, 1.3)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        assert!((bodies[1].position.y - 0.5).abs() < 1e-4);
+        assert!((bodies[2].position.y - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn walking_into_a_player_pushes_them() {
+        let mut bodies = vec![floor(), player(0., 0.5), player(1., 0.5)];
+
+        for _ in 0..10 {
+            bodies[1].position.x += 0.1;
+            solve(&mut bodies, SOLVER_ITERATIONS);
+        }
+
+        assert!((bodies[2].position.x - bodies[1].position.x - 1.).abs() < 1e-4);
+        assert!(bodies[2].position.x > 1.4);
+    }
+
+    #[test]
+    fn pushed_player_stops_at_a_wall() {
+        let mut bodies = vec![floor(), wall(3.), player(0., 0.5), player(1.5, 0.5)];
+
+        for _ in 0..30 {
+            bodies[2].position.x += 0.1;
+            solve(&mut bodies, SOLVER_ITERATIONS);
+        }
+
+        assert!(bodies[3].position.x <= 2. + 1e-3);
+        assert!(bodies[2].position.x <= 1. + 1e-3);
+    }
+
+    #[test]
+    fn players_without_player_collisions_pass_through_each_other() {
+        let ghost = |x: f32| SolverBody {
+            collider: leak(
+                Collider::new(PLAYER_SIZE).with_layers(LAYER_PLAYER, LAYER_ALL & !LAYER_PLAYER),
+            ),
+            ..player(x, 0.5)
+        };
+        let mut bodies = vec![floor(), ghost(0.), ghost(0.5)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        assert_eq!(bodies[1].position, Vec2::new(0., 0.5));
+        assert_eq!(bodies[2].position, Vec2::new(0.5, 0.5));
+    }
+
+    #[test]
+    fn lighter_players_are_pushed_further() {
+        let mut light = player(0., 0.5);
+        let mut heavy = player(0.9, 0.5);
+        light.snap = false;
+        heavy.snap = false;
+        heavy.inverse_mass = 0.5;
+        let mut bodies = vec![light, heavy];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        // the 0.1 overlap is split 2:1 towards the body with half the mass
+        assert!((bodies[0].position.x + 0.1 * 2. / 3.).abs() < 1e-4);
+        assert!((bodies[1].position.x - (0.9 + 0.1 / 3.)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn solving_is_deterministic() {
+        let run = || {
+            let mut bodies = vec![floor(), wall(3.), player(0., 0.7), player(0.4, 1.6)];
+
+            for frame in 0..60 {
+                bodies[2].position += Vec2::new(0.07, -FALL_STEP);
+                bodies[3].position +=
+                    Vec2::new(if frame % 2 == 0 { -0.03 } else { 0.05 }, -FALL_STEP);
+                solve(&mut bodies, SOLVER_ITERATIONS);
             }
+
+            bodies.iter().map(|b| b.position).collect::<Vec<_>>()
         };
 
-        let max = edge_distance.max_element().abs();
+        assert_eq!(run(), run());
+    }
 
-        let solid = solids.contains(e1) && solids.contains(e2);
+    /// 45° slope two units wide with its bottom on y = 0, rising towards x = 1
+    fn ramp() -> SolverBody<'static> {
+        SolverBody {
+            position: Vec2::new(0., 1.),
+            collider: leak(Collider::slope(2., SlopeAngle::Deg45, true)),
+            inverse_mass: 0.,
+            snap: false,
+        }
+    }
+
+    fn ball(x: f32, y: f32, radius: f32, inverse_mass: f32) -> SolverBody<'static> {
+        SolverBody {
+            position: Vec2::new(x, y),
+            collider: leak(Collider::circle(radius)),
+            inverse_mass,
+            snap: true,
+        }
+    }
+
+    #[test]
+    fn players_stand_on_the_uphill_corner_of_a_slope() {
+        let mut bodies = vec![ramp(), player(0., 1.)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        // the corner at x = 0.5 sits on the surface at y = 1.5
+        assert!((bodies[1].position.y - 2.).abs() < 1e-4);
+        assert_eq!(bodies[1].position.x, 0.);
+    }
+
+    #[test]
+    fn walking_downhill_snaps_onto_the_slope() {
+        let mut bodies = vec![ramp(), player(0., 2.05)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        assert!((bodies[1].position.y - 2.).abs() < 1e-4);
+
+        // rising bodies don't snap, so jumping off a slope isn't pulled back down
+        let mut bodies = vec![ramp(), player(0., 2.05)];
+        bodies[1].snap = false;
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        assert_eq!(bodies[1].position.y, 2.05);
+    }
+
+    #[test]
+    fn slopes_report_ground_under_a_player() {
+        let (side, _) = intersect(
+            &Collider::new(PLAYER_SIZE),
+            Vec2::new(-0.5, 0.5),
+            true,
+            ramp().collider,
+            Vec2::new(0., 1.),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(side, CollidingSide::Bottom);
+    }
+
+    #[test]
+    fn circles_rest_on_the_floor() {
+        let mut bodies = vec![floor(), ball(0.3, 0.4, 0.5, 1.)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
+
+        assert!((bodies[1].position.y - 0.5).abs() < 1e-4);
+        assert_eq!(bodies[1].position.x, 0.3);
+    }
+
+    #[test]
+    fn fixed_circles_block_players_from_the_side() {
+        let mut bodies = vec![floor(), ball(0., 0.5, 0.5, 0.), player(0.9, 0.5)];
+
+        solve(&mut bodies, SOLVER_ITERATIONS);
 
-        c1.add_collision(e2, collisions.0, max, solid);
-        c2.add_collision(e1, collisions.1, max, solid);
+        assert!((bodies[2].position.x - 1.).abs() < 1e-4);
+        assert!((bodies[2].position.y - 0.5).abs() < 1e-4);
     }
 }
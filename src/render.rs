@@ -0,0 +1,145 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        texture::ImageSampler,
+        view::RenderLayers,
+    },
+    window::PrimaryWindow,
+};
+
+use crate::{
+    settings::{ScaleSnap, Settings},
+    Persistent, MIN_WINDOW_SIZE,
+};
+
+/// the game is drawn at this many pixels across and up, then blown up to fit the window
+pub const RENDER_SIZE: u32 = MIN_WINDOW_SIZE as u32;
+
+/// only the upscaled picture of the game lives on this layer, so the upscale camera sees
+/// nothing else
+const UPSCALE_LAYER: usize = 1;
+
+pub struct PixelPerfectPlugin;
+
+impl Plugin for PixelPerfectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, scale_to_window);
+    }
+}
+
+/// the low resolution image every game camera renders into
+#[derive(Resource, Debug, Clone)]
+pub struct PixelTarget(pub Handle<Image>);
+
+impl PixelTarget {
+    pub fn render_target(&self) -> RenderTarget {
+        RenderTarget::Image(self.0.clone())
+    }
+}
+
+#[derive(Component, Debug)]
+struct UpscaledScreen;
+
+/// creates the render target and the camera that shows it in the window, runs before anything
+/// spawns a game camera
+pub fn setup_pixel_target(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: RENDER_SIZE,
+        height: RENDER_SIZE,
+        depth_or_array_layers: 1,
+    };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("pixel_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        sampler: ImageSampler::nearest(),
+        ..default()
+    };
+    image.resize(size);
+
+    let handle = images.add(image);
+
+    commands.spawn((
+        UpscaledScreen,
+        Persistent,
+        SpriteBundle {
+            texture: handle.clone(),
+            ..default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+    ));
+
+    // drawn after the game cameras, the black around the picture is the letterbox
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 10,
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                ..default()
+            },
+            ..default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+    ));
+
+    commands.insert_resource(PixelTarget(handle));
+}
+
+/// scales the picture to fit the window, by default by the biggest whole number of physical
+/// pixels so every game pixel is the same size on screen whatever the DPI or window size
+fn scale_to_window(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    mut screens: Query<&mut Transform, With<UpscaledScreen>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let physical = window.physical_size();
+
+    let (scale, offset) = match settings.scale_snap {
+        ScaleSnap::Integer => {
+            let scale = (physical.min_element() / RENDER_SIZE).max(1);
+
+            // with an odd number of spare pixels the picture's edge would land between two
+            // pixels, so nudge it half a pixel onto the grid
+            let spare = physical.saturating_sub(UVec2::splat(RENDER_SIZE * scale));
+            let offset = Vec2::new((spare.x % 2) as f32, (spare.y % 2) as f32) * 0.5;
+
+            (scale as f32, offset)
+        }
+        ScaleSnap::Fit => (
+            (physical.min_element() as f32 / RENDER_SIZE as f32).max(1.),
+            Vec2::ZERO,
+        ),
+    };
+
+    let scale_factor = window.scale_factor();
+
+    let target = Transform {
+        translation: (offset / scale_factor).extend(0.),
+        scale: Vec3::splat(scale / scale_factor),
+        ..default()
+    };
+
+    for mut transform in &mut screens {
+        if *transform != target {
+            *transform = target;
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use bevy_ggrs::{GgrsSchedule, LocalInputs, LocalPlayers, PlayerInputs, ReadInputs};
+
+use crate::{
+    input::PlayerInput,
+    rollback::{advance_rollback_frame, RollbackFrame},
+    states::waiting_lobby::GameConfig,
+    storage, AppState, Config,
+};
+
+/// bumped whenever the layout of a replay file changes, older replays are refused
+const REPLAY_VERSION: u8 = 1;
+
+/// version, player count, packed config and the player collisions flag
+const HEADER_LEN: usize = 11;
+
+const INPUT_LEN: usize = std::mem::size_of::<PlayerInput>();
+
+/// records the inputs of every session so it can be watched again from the past games screen,
+/// and feeds them back in place of the local devices while one is being watched
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::FullLobby),
+            start_recording.run_if(not(resource_exists::<ReplayPlayback>)),
+        )
+        .add_systems(
+            GgrsSchedule,
+            record_inputs
+                .after(advance_rollback_frame)
+                .run_if(resource_exists::<ReplayRecorder>),
+        )
+        .add_systems(
+            ReadInputs,
+            read_replay_inputs.run_if(resource_exists::<ReplayPlayback>),
+        );
+    }
+}
+
+/// every input a session ran on, which is all it takes to simulate it again from the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub config: GameConfig,
+    /// how many inputs make up a frame
+    pub players: usize,
+    /// one input per player for every frame, oldest frame first
+    inputs: Vec<PlayerInput>,
+}
+
+impl Replay {
+    pub fn new(config: GameConfig) -> Self {
+        Self {
+            config,
+            players: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// the inputs simulated on a frame, counted from 0, replacing whatever a rolled back
+    /// simulation of that frame and the ones after it had recorded
+    fn record(&mut self, frame: usize, inputs: &[PlayerInput]) {
+        self.players = inputs.len();
+        self.inputs
+            .resize(frame * self.players, PlayerInput::default());
+        self.inputs.extend_from_slice(inputs);
+    }
+
+    pub fn frames(&self) -> usize {
+        self.inputs.len().checked_div(self.players).unwrap_or(0)
+    }
+
+    /// every player's input on a frame, empty once the replay has run out
+    pub fn frame(&self, frame: usize) -> &[PlayerInput] {
+        let start = frame * self.players;
+        self.inputs
+            .get(start..start + self.players)
+            .unwrap_or_default()
+    }
+
+    /// inputs are written in this machine's byte order, replays stay where they were recorded
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.inputs.len() * INPUT_LEN);
+        bytes.push(REPLAY_VERSION);
+        bytes.push(self.players as u8);
+        bytes.extend_from_slice(&self.config.as_u64().to_le_bytes());
+        bytes.push(self.config.player_collisions as u8);
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.inputs));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let Some((header, inputs)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+            return Err("replay is too short to have a header".to_string());
+        };
+
+        let [version, players, config @ .., collisions] = *header;
+
+        if version != REPLAY_VERSION {
+            return Err(format!(
+                "replay is from another version of the game ({version} != {REPLAY_VERSION})"
+            ));
+        }
+
+        let players = players as usize;
+
+        if players == 0 || inputs.len() % (players * INPUT_LEN) != 0 {
+            return Err("replay doesn't hold a whole number of frames".to_string());
+        }
+
+        Ok(Self {
+            config: GameConfig::from_u64(u64::from_le_bytes(config), collisions != 0),
+            players,
+            inputs: inputs
+                .chunks_exact(INPUT_LEN)
+                .map(bytemuck::pod_read_unaligned)
+                .collect(),
+        })
+    }
+
+    /// writes the replay next to the match history, returning the name it can be loaded by
+    pub fn save(&self, date: u64) -> Option<String> {
+        let name = format!("{date}.replay");
+        let path = replay_file(&name)?;
+
+        match storage::write_atomic(&path, &self.to_bytes()) {
+            Ok(()) => {
+                info!("Saved replay to {}", path.display());
+                Some(name)
+            }
+            Err(e) => {
+                error!("Failed to save replay to {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn load(name: &str) -> Option<Self> {
+        let path = replay_file(name)?;
+
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read {}: {e}", path.display());
+                return None;
+            }
+        };
+
+        match Self::from_bytes(&bytes) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                warn!("Failed to load replay {}: {e}", path.display());
+                None
+            }
+        }
+    }
+}
+
+fn replay_file(name: &str) -> Option<PathBuf> {
+    storage::data_file(&format!("replays/{name}"))
+}
+
+/// the replay of the session being played, saved with its match record when the session ends
+#[derive(Resource, Debug)]
+pub struct ReplayRecorder(pub Replay);
+
+/// the replay being watched, its inputs replace the local ones until it runs out
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback(pub Replay);
+
+/// lets the session tell whether it's a replay being watched and when that replay runs out
+#[derive(SystemParam)]
+pub struct WatchedReplay<'w> {
+    frame: Res<'w, RollbackFrame>,
+    playback: Option<Res<'w, ReplayPlayback>>,
+}
+
+impl WatchedReplay<'_> {
+    pub fn is_watching(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// every recorded frame has been simulated
+    pub fn is_over(&self) -> bool {
+        self.playback
+            .as_ref()
+            .is_some_and(|playback| self.frame.0 as usize >= playback.0.frames())
+    }
+}
+
+fn start_recording(mut commands: Commands, config: Res<GameConfig>) {
+    commands.insert_resource(ReplayRecorder(Replay::new(*config)));
+}
+
+/// runs on every simulated frame, a resimulated one overwrites what its mispredicted run recorded
+fn record_inputs(
+    frame: Res<RollbackFrame>,
+    inputs: Res<PlayerInputs<Config>>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let inputs = inputs.iter().map(|(input, _)| *input).collect::<Vec<_>>();
+
+    // the frame counter is already advanced, so the first frame is 1
+    recorder.0.record(frame.0 as usize - 1, &inputs);
+}
+
+/// replays run in a local session without rollbacks, so the frame about to be simulated is
+/// always the one after the last
+fn read_replay_inputs(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    frame: Res<RollbackFrame>,
+    local_players: Res<LocalPlayers>,
+) {
+    let inputs = playback.0.frame(frame.0 as usize);
+
+    let local_inputs = local_players
+        .0
+        .iter()
+        .map(|handle| (*handle, inputs.get(*handle).copied().unwrap_or_default()))
+        .collect::<HashMap<_, _>>();
+
+    commands.insert_resource(LocalInputs::<Config>(local_inputs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{INPUT_JUMP, INPUT_LEFT, INPUT_RIGHT};
+
+    fn input(buttons: u16) -> PlayerInput {
+        PlayerInput::new(buttons, 0.)
+    }
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(GameConfig {
+            seed: 3,
+            difficulty: 2,
+            player_collisions: false,
+        });
+        replay.record(0, &[input(INPUT_LEFT), input(0)]);
+        replay.record(1, &[input(INPUT_JUMP), PlayerInput::new(0, -0.5)]);
+        replay
+    }
+
+    #[test]
+    fn replays_round_trip_through_bytes() {
+        let replay = replay();
+
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()), Ok(replay));
+    }
+
+    #[test]
+    fn resimulated_frames_replace_what_they_recorded() {
+        let mut replay = replay();
+        replay.record(1, &[input(INPUT_RIGHT), input(INPUT_RIGHT)]);
+
+        assert_eq!(replay.frames(), 2);
+        assert_eq!(replay.frame(0), [input(INPUT_LEFT), input(0)]);
+        assert_eq!(replay.frame(1), [input(INPUT_RIGHT), input(INPUT_RIGHT)]);
+        assert!(replay.frame(2).is_empty());
+    }
+
+    #[test]
+    fn broken_replays_are_refused() {
+        let bytes = replay().to_bytes();
+
+        assert!(Replay::from_bytes(&bytes[..HEADER_LEN - 1]).is_err());
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut other_version = bytes.clone();
+        other_version[0] = REPLAY_VERSION + 1;
+        assert!(Replay::from_bytes(&other_version).is_err());
+    }
+}
@@ -1,15 +1,17 @@
 use bevy::prelude::Resource;
 
+use crate::settings::MAX_WINDOW_SCALE;
+
 #[derive(Debug, Resource)]
 pub struct WindowScale(pub u32);
 
 impl WindowScale {
-    pub fn new() -> Self {
-        Self(1)
+    pub fn new(scale: u32) -> Self {
+        Self(scale.clamp(1, MAX_WINDOW_SCALE))
     }
 
     pub fn increase(&mut self) -> u32 {
-        self.0 += 1;
+        self.0 = MAX_WINDOW_SCALE.min(self.0 + 1);
         self.0
     }
 
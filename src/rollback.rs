@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy_ggrs::{GgrsApp, GgrsSchedule};
+
+use crate::{physics::PhysicsSet, states::full_lobby::in_lobby::InLobbySet, AppState};
+
+/// how far back ggrs can roll, its default prediction window which no session changes
+pub const MAX_ROLLBACK_FRAMES: u32 = 8;
+
+/// how often the rollback schedule runs, bevy_ggrs' default
+pub const ROLLBACK_FPS: u32 = 60;
+
+/// numbers the frames of the rollback schedule so sounds and effects it starts can be told apart
+/// from the same ones started again when a frame is resimulated
+pub struct RollbackFramePlugin;
+
+impl Plugin for RollbackFramePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackFrame>()
+            .init_resource::<SimulatedFrames>()
+            .rollback_resource_with_copy::<RollbackFrame>()
+            .add_systems(First, clear_simulated_frames)
+            .add_systems(
+                GgrsSchedule,
+                advance_rollback_frame
+                    .before(InLobbySet::Update)
+                    .before(PhysicsSet),
+            )
+            .add_systems(OnExit(AppState::FullLobby), reset_rollback_frame);
+    }
+}
+
+/// frames simulated by the rollback schedule, rolled back with everything else so a resimulated
+/// frame gets the same number it had the first time
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct RollbackFrame(pub u32);
+
+/// every frame the rollback schedule ran this update, including ones it went back and redid
+#[derive(Resource, Debug, Default)]
+pub struct SimulatedFrames(pub Vec<u32>);
+
+fn clear_simulated_frames(mut simulated: ResMut<SimulatedFrames>) {
+    simulated.0.clear();
+}
+
+pub(crate) fn advance_rollback_frame(
+    mut frame: ResMut<RollbackFrame>,
+    mut simulated: ResMut<SimulatedFrames>,
+) {
+    frame.0 += 1;
+    simulated.0.push(frame.0);
+}
+
+fn reset_rollback_frame(mut frame: ResMut<RollbackFrame>) {
+    frame.0 = 0;
+}
+
+/// events sent from the rollback schedule that are only read once their frame is too old to be
+/// rolled back, for anything that can't be undone like writing to disk
+#[derive(Resource, Debug)]
+pub struct ConfirmedEvents<T> {
+    /// by the frame that sent them, oldest first
+    pending: Vec<(u32, T)>,
+}
+
+impl<T> Default for ConfirmedEvents<T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<T> ConfirmedEvents<T> {
+    pub fn send(&mut self, frame: u32, event: T) {
+        self.pending.push((frame, event));
+    }
+}
+
+pub trait ConfirmedEventApp {
+    /// adds `T` as an event that the rollback schedule sends through `ConfirmedEvents<T>`
+    fn add_confirmed_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl ConfirmedEventApp for App {
+    fn add_confirmed_event<T: Event>(&mut self) -> &mut Self {
+        self.add_event::<T>()
+            .init_resource::<ConfirmedEvents<T>>()
+            .add_systems(
+                GgrsSchedule,
+                forget_resimulated_events::<T>
+                    .after(advance_rollback_frame)
+                    .before(InLobbySet::Update)
+                    .before(PhysicsSet),
+            )
+            .add_systems(Update, release_confirmed_events::<T>)
+            .add_systems(OnExit(AppState::FullLobby), clear_confirmed_events::<T>)
+    }
+}
+
+/// a frame that runs again sends its events again, if it still does
+fn forget_resimulated_events<T: Event>(
+    frame: Res<RollbackFrame>,
+    mut confirmed: ResMut<ConfirmedEvents<T>>,
+) {
+    confirmed.pending.retain(|(sent, _)| *sent < frame.0);
+}
+
+pub fn release_confirmed_events<T: Event>(
+    frame: Res<RollbackFrame>,
+    mut confirmed: ResMut<ConfirmedEvents<T>>,
+    mut events: EventWriter<T>,
+) {
+    let confirmed_len = confirmed
+        .pending
+        .iter()
+        .take_while(|(sent, _)| sent + MAX_ROLLBACK_FRAMES <= frame.0)
+        .count();
+
+    events.send_batch(
+        confirmed
+            .pending
+            .drain(..confirmed_len)
+            .map(|(_, event)| event),
+    );
+}
+
+fn clear_confirmed_events<T: Event>(mut confirmed: ResMut<ConfirmedEvents<T>>) {
+    confirmed.pending.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[derive(Event, Debug, Clone, Copy, PartialEq)]
+    struct Cleared(u32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<RollbackFrame>();
+        world.init_resource::<ConfirmedEvents<Cleared>>();
+        world.init_resource::<Events<Cleared>>();
+        world
+    }
+
+    /// runs one rollback frame, sending `event` from it if there is one
+    fn simulate(world: &mut World, frame: u32, event: Option<Cleared>) {
+        world.resource_mut::<RollbackFrame>().0 = frame;
+        world.run_system_once(forget_resimulated_events::<Cleared>);
+
+        if let Some(event) = event {
+            world
+                .resource_mut::<ConfirmedEvents<Cleared>>()
+                .send(frame, event);
+        }
+    }
+
+    fn released(world: &mut World) -> Vec<Cleared> {
+        world.run_system_once(release_confirmed_events::<Cleared>);
+        world.resource_mut::<Events<Cleared>>().drain().collect()
+    }
+
+    #[test]
+    fn events_wait_until_their_frame_cannot_be_rolled_back() {
+        let mut world = world();
+
+        simulate(&mut world, 1, Some(Cleared(1)));
+
+        for frame in 2..MAX_ROLLBACK_FRAMES + 1 {
+            simulate(&mut world, frame, None);
+            assert_eq!(released(&mut world), []);
+        }
+
+        simulate(&mut world, MAX_ROLLBACK_FRAMES + 1, None);
+        assert_eq!(released(&mut world), [Cleared(1)]);
+        assert_eq!(released(&mut world), []);
+    }
+
+    #[test]
+    fn resimulated_frames_send_their_events_once() {
+        let mut world = world();
+
+        simulate(&mut world, 1, Some(Cleared(1)));
+        simulate(&mut world, 2, None);
+        // rolled back to before frame 1 and ran it again, with the same outcome
+        simulate(&mut world, 1, Some(Cleared(1)));
+
+        for frame in 2..=MAX_ROLLBACK_FRAMES + 1 {
+            simulate(&mut world, frame, None);
+        }
+
+        assert_eq!(released(&mut world), [Cleared(1)]);
+    }
+
+    #[test]
+    fn events_that_did_not_happen_after_a_rollback_are_dropped() {
+        let mut world = world();
+
+        simulate(&mut world, 1, None);
+        simulate(&mut world, 2, Some(Cleared(2)));
+        // the other player's real input meant nobody reached the door on frame 2
+        simulate(&mut world, 2, None);
+        simulate(&mut world, 3, Some(Cleared(3)));
+
+        for frame in 4..=MAX_ROLLBACK_FRAMES + 3 {
+            simulate(&mut world, frame, None);
+        }
+
+        assert_eq!(released(&mut world), [Cleared(3)]);
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{states::full_lobby::LevelCleared, storage};
+
+/// bumped whenever `Progress` changes shape, older files are migrated when they're loaded
+const SAVE_VERSION: u32 = 2;
+
+pub const SAVE_SLOTS: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelProgress {
+    /// fastest clear in seconds, `None` until the level has been cleared
+    pub best_time: Option<f32>,
+    /// ids of the collectibles picked up in the level
+    pub collectibles: BTreeSet<u32>,
+}
+
+/// everything a campaign remembers between sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    /// every level up to and including this one can be played
+    pub unlocked: u32,
+    pub levels: BTreeMap<u32, LevelProgress>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    progress: Progress,
+}
+
+/// the first saves had no version and only kept a best time per level
+#[derive(Deserialize)]
+struct SaveFileV0 {
+    #[serde(default)]
+    unlocked: u32,
+    #[serde(default)]
+    best_times: BTreeMap<u32, f32>,
+}
+
+impl From<SaveFileV0> for Progress {
+    fn from(file: SaveFileV0) -> Self {
+        let levels = file
+            .best_times
+            .into_iter()
+            .map(|(level, time)| {
+                let progress = LevelProgress {
+                    best_time: Some(time),
+                    ..default()
+                };
+                (level, progress)
+            })
+            .collect();
+
+        Self {
+            unlocked: file.unlocked,
+            levels,
+        }
+    }
+}
+
+/// read first to find out how the rest of the file should be parsed
+#[derive(Deserialize)]
+struct SaveHeader {
+    #[serde(default)]
+    version: u32,
+}
+
+impl Progress {
+    /// the progress in a save slot, empty if the slot hasn't been used or can't be read
+    pub fn load(slot: usize) -> Self {
+        let Some(path) = save_file(slot) else {
+            return Self::default();
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Failed to read {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+
+        match migrate(&contents) {
+            Ok(progress) => progress,
+            Err(e) => {
+                warn!("Failed to load save slot {slot}: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, slot: usize) {
+        let Some(path) = save_file(slot) else {
+            return;
+        };
+
+        let file = SaveFile {
+            version: SAVE_VERSION,
+            progress: self.clone(),
+        };
+
+        match storage::save_ron(&path, &file) {
+            Ok(()) => info!("Saved slot {slot} to {}", path.display()),
+            Err(e) => error!("Failed to save slot {slot} to {}: {e}", path.display()),
+        }
+    }
+
+    pub fn levels_cleared(&self) -> usize {
+        self.levels
+            .values()
+            .filter(|level| level.best_time.is_some())
+            .count()
+    }
+
+    pub fn collectibles_found(&self) -> usize {
+        self.levels
+            .values()
+            .map(|level| level.collectibles.len())
+            .sum()
+    }
+
+    /// marks a level as cleared and unlocks the next one, returning whether the time is a new best
+    pub fn record_clear(&mut self, level: u32, time: f32) -> bool {
+        self.unlocked = self.unlocked.max(level + 1);
+
+        let progress = self.levels.entry(level).or_default();
+        let new_best = progress.best_time.is_none_or(|best| time < best);
+
+        if new_best {
+            progress.best_time = Some(time);
+        }
+
+        new_best
+    }
+
+    /// compact form sent from the config bearer to the receiver during the handshake
+    pub fn to_bytes(&self) -> Vec<u8> {
+        ron::to_string(self).map_or_else(
+            |e| {
+                error!("Failed to serialize progress: {e}");
+                Vec::new()
+            },
+            String::into_bytes,
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let contents = std::str::from_utf8(bytes).ok()?;
+        ron::from_str(contents).ok()
+    }
+}
+
+fn save_file(slot: usize) -> Option<PathBuf> {
+    storage::data_file(&format!("save_{slot}.ron"))
+}
+
+/// parses a save file written by any version of the game into the current `Progress`
+fn migrate(contents: &str) -> Result<Progress, String> {
+    let header: SaveHeader = ron::from_str(contents).map_err(|e| e.to_string())?;
+
+    match header.version {
+        // version 1 had no collectibles, which load as none found
+        1 | SAVE_VERSION => ron::from_str::<SaveFile>(contents)
+            .map(|file| file.progress)
+            .map_err(|e| e.to_string()),
+        // each older layout gets its own struct and an arm here that converts it
+        0 => ron::from_str::<SaveFileV0>(contents)
+            .map(Progress::from)
+            .map_err(|e| e.to_string()),
+        version => Err(format!(
+            "save is from a newer version of the game ({version} > {SAVE_VERSION})"
+        )),
+    }
+}
+
+/// the campaign being played, `slot` is `None` for the receiver in an online game since the
+/// progress belongs to the host
+#[derive(Resource, Debug, Clone)]
+pub struct Campaign {
+    pub slot: Option<usize>,
+    pub progress: Progress,
+}
+
+pub fn record_level_clears(
+    mut events: EventReader<LevelCleared>,
+    campaign: Option<ResMut<Campaign>>,
+) {
+    let Some(mut campaign) = campaign else {
+        events.clear();
+        return;
+    };
+
+    for event in events.read() {
+        if campaign.progress.record_clear(event.level, event.time) {
+            info!("New best time on level {}: {:.2}s", event.level, event.time);
+        }
+
+        if let Some(slot) = campaign.slot {
+            campaign.progress.save(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_round_trip() {
+        let mut progress = Progress::default();
+        progress.record_clear(0, 12.5);
+        progress.record_clear(1, 30.);
+        progress.levels.entry(1).or_default().collectibles = BTreeSet::from([2, 5]);
+
+        let contents = ron::to_string(&SaveFile {
+            version: SAVE_VERSION,
+            progress: progress.clone(),
+        })
+        .unwrap();
+
+        assert_eq!(migrate(&contents), Ok(progress));
+    }
+
+    #[test]
+    fn unversioned_saves_are_migrated() {
+        let progress = migrate("(unlocked: 2, best_times: {0: 12.5, 1: 30.0})").unwrap();
+
+        assert_eq!(progress.unlocked, 2);
+        assert_eq!(progress.levels_cleared(), 2);
+        assert_eq!(progress.levels[&0].best_time, Some(12.5));
+        assert_eq!(progress.levels[&1].best_time, Some(30.));
+    }
+
+    #[test]
+    fn saves_without_collectibles_are_migrated() {
+        let progress =
+            migrate("(version: 1, progress: (unlocked: 1, levels: {0: (best_time: Some(9.5))}))")
+                .unwrap();
+
+        assert_eq!(progress.levels[&0].best_time, Some(9.5));
+        assert!(progress.levels[&0].collectibles.is_empty());
+        assert_eq!(progress.collectibles_found(), 0);
+    }
+
+    #[test]
+    fn saves_from_newer_versions_are_refused() {
+        let contents = format!("(version: {}, progress: ())", SAVE_VERSION + 1);
+
+        assert!(migrate(&contents).is_err());
+        assert!(migrate("not a save").is_err());
+    }
+
+    #[test]
+    fn only_faster_clears_are_new_bests() {
+        let mut progress = Progress::default();
+
+        assert!(progress.record_clear(0, 20.));
+        assert!(!progress.record_clear(0, 25.));
+        assert!(progress.record_clear(0, 15.));
+        assert_eq!(progress.levels[&0].best_time, Some(15.));
+        assert_eq!(progress.unlocked, 1);
+    }
+
+    #[test]
+    fn progress_survives_the_handshake_bytes() {
+        let mut progress = Progress::default();
+        progress.record_clear(3, 8.25);
+
+        assert_eq!(Progress::from_bytes(&progress.to_bytes()), Some(progress));
+    }
+}
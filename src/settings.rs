@@ -0,0 +1,200 @@
+use bevy::{
+    audio::Volume,
+    prelude::*,
+    window::{PrimaryWindow, WindowMode, WindowResized, WindowResolution},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{resources::WindowScale, storage, MIN_WINDOW_SIZE};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+pub const MAX_WINDOW_SCALE: u32 = 6;
+pub const MAX_INPUT_DELAY: usize = 8;
+pub const MAX_DISPLAY_NAME_LEN: usize = 16;
+
+/// everything the settings screen can change, persisted in the config dir next to the bindings
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window_scale: u32,
+    /// logical size the window was last left at, `None` to size it by `window_scale`
+    pub window_size: Option<(f32, f32)>,
+    pub fullscreen: bool,
+    pub scale_snap: ScaleSnap,
+    /// volumes are all in 0..=1, music and effects are scaled by the master volume
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// frames of input delay for online games, more hides lag spikes but makes controls feel slower
+    pub input_delay: usize,
+    /// host and port of the matchbox signaling server
+    pub signaling_server: String,
+    pub display_name: String,
+    /// give each player half the screen when they get too far apart to share one camera
+    pub split_screen: bool,
+    /// whether players bump into each other, online the host's choice is used for both
+    pub player_collisions: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window_scale: 1,
+            window_size: None,
+            fullscreen: false,
+            scale_snap: ScaleSnap::Integer,
+            master_volume: 1.,
+            music_volume: 0.8,
+            sfx_volume: 0.8,
+            input_delay: 2,
+            signaling_server: "3.128.79.14:3536".into(),
+            display_name: "Player".into(),
+            split_screen: true,
+            player_collisions: true,
+        }
+    }
+}
+
+/// how the picture is blown up when the window isn't a whole multiple of the render size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScaleSnap {
+    /// whole pixels only, with wider black bars
+    #[default]
+    Integer,
+    /// fills as much of the window as possible, some pixels end up a little bigger than others
+    Fit,
+}
+
+impl Settings {
+    /// the saved settings, or the defaults when there are none
+    pub fn load() -> Self {
+        let mut settings: Self = storage::config_file(SETTINGS_FILE)
+            .and_then(|path| storage::load_ron(&path))
+            .unwrap_or_default();
+
+        // a hand edited file shouldn't be able to put the game in a broken state
+        settings.window_scale = settings.window_scale.clamp(1, MAX_WINDOW_SCALE);
+        settings.input_delay = settings.input_delay.min(MAX_INPUT_DELAY);
+        settings.master_volume = settings.master_volume.clamp(0., 1.);
+        settings.music_volume = settings.music_volume.clamp(0., 1.);
+        settings.sfx_volume = settings.sfx_volume.clamp(0., 1.);
+        settings.window_size = settings
+            .window_size
+            .filter(|(width, height)| width.is_finite() && height.is_finite())
+            .map(|(width, height)| (width.max(MIN_WINDOW_SIZE), height.max(MIN_WINDOW_SIZE)));
+
+        settings
+    }
+
+    /// the size the window opens at, the way it was left last time
+    pub fn window_resolution(&self) -> WindowResolution {
+        let scaled = MIN_WINDOW_SIZE * self.window_scale as f32;
+        let (width, height) = self.window_size.unwrap_or((scaled, scaled));
+
+        WindowResolution::new(width, height)
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.fullscreen {
+            true => WindowMode::BorderlessFullscreen,
+            false => WindowMode::Windowed,
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = storage::config_file(SETTINGS_FILE) else {
+            return;
+        };
+
+        match storage::save_ron(&path, self) {
+            Ok(()) => info!("Saved settings to {}", path.display()),
+            Err(e) => error!("Failed to save settings to {}: {e}", path.display()),
+        }
+    }
+}
+
+/// resizes the window whenever the scale changes, either from the settings screen or Ctrl +/-,
+/// and remembers it for next time
+pub fn apply_window_scale(
+    scale: Res<WindowScale>,
+    mut settings: ResMut<Settings>,
+    mut windows: Query<&mut Window>,
+) {
+    // the window opened at its saved size, which may not be a multiple of the scale
+    if scale.is_added() {
+        return;
+    }
+
+    let mut window = windows.single_mut();
+
+    if window.mode == WindowMode::Windowed {
+        window.resolution.set(
+            MIN_WINDOW_SIZE * scale.0 as f32,
+            MIN_WINDOW_SIZE * scale.0 as f32,
+        );
+    }
+
+    if settings.window_scale != scale.0 {
+        settings.window_scale = scale.0;
+        settings.save();
+    }
+}
+
+/// switches between the window and borderless fullscreen when the setting changes
+pub fn apply_window_mode(settings: Res<Settings>, mut windows: Query<&mut Window>) {
+    let mut window = windows.single_mut();
+    let mode = settings.window_mode();
+
+    if window.mode != mode {
+        window.mode = mode;
+    }
+}
+
+/// how long the window has to stay the same size before it's saved, so dragging an edge doesn't
+/// write the settings file every frame
+const RESIZE_SAVE_DELAY: f32 = 0.5;
+
+/// remembers the size of the window once the player is done resizing it
+pub fn remember_window_size(
+    mut resized: EventReader<WindowResized>,
+    mut settings: ResMut<Settings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time<Real>>,
+    mut pending: Local<Option<Timer>>,
+) {
+    if resized.read().last().is_some() {
+        *pending = Some(Timer::from_seconds(RESIZE_SAVE_DELAY, TimerMode::Once));
+    }
+
+    let Some(timer) = pending.as_mut() else {
+        return;
+    };
+
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    *pending = None;
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    // fullscreen is the size of the monitor, the window should come back to where it was
+    if window.mode != WindowMode::Windowed {
+        return;
+    }
+
+    let size = (window.width(), window.height());
+
+    if settings.window_size != Some(size) {
+        settings.window_size = Some(size);
+        settings.save();
+    }
+}
+
+/// sounds pick up the global volume when they start playing
+pub fn apply_master_volume(settings: Res<Settings>, mut volume: ResMut<GlobalVolume>) {
+    volume.volume = Volume::new(settings.master_volume);
+}
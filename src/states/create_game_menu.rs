@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::{
+    audio::{PlaySound, Sfx},
+    despawn_all_but_camera,
+    input::GamepadMenuInput,
+    save::{Campaign, Progress, SAVE_SLOTS},
+    settings::Settings,
+    states::{
+        local_lobby::LocalPlayerCount,
+        waiting_lobby::{CMRole, GameConfig},
+    },
+    AppState,
+};
+
+const FONT_SIZE: f32 = 8.;
+const SELECTED_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+const IDLE_COLOR: Color = Color::WHITE;
+const HINT_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+
+pub struct CreateGameMenuPlugin;
+
+impl Plugin for CreateGameMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::CreateGameMenu),
+            (despawn_all_but_camera, setup)
+                .chain()
+                .in_set(CreateGameMenuSet::Setup),
+        )
+        .add_systems(
+            Update,
+            (read_menu_input, update_options, update_slot_label)
+                .chain()
+                .in_set(CreateGameMenuSet::Update),
+        )
+        .configure_sets(
+            Update,
+            CreateGameMenuSet::Update.run_if(in_state(AppState::CreateGameMenu)),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CreateGameMenuSet {
+    Setup,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GameMode {
+    Online,
+    CouchCoop,
+    Solo,
+}
+
+impl GameMode {
+    const ALL: [GameMode; 3] = [GameMode::Online, GameMode::CouchCoop, GameMode::Solo];
+
+    fn label(&self) -> &'static str {
+        match self {
+            GameMode::Online => "Host online game",
+            GameMode::CouchCoop => "Couch co-op",
+            GameMode::Solo => "Solo practice",
+        }
+    }
+}
+
+#[derive(Debug, Resource, Clone)]
+struct CreateGameMenu {
+    selected: usize,
+    slot: usize,
+    /// what's in each save slot, so picking one can show how far it got
+    slots: Vec<Progress>,
+}
+
+#[derive(Component, Debug)]
+struct ModeOption(usize);
+
+#[derive(Component, Debug)]
+struct SlotLabel;
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(CreateGameMenu {
+        selected: 0,
+        slot: 0,
+        slots: (0..SAVE_SLOTS).map(Progress::load).collect(),
+    });
+
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: IDLE_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.),
+                ..default()
+            },
+            background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("New game", style.clone()));
+
+            for (i, mode) in GameMode::ALL.iter().enumerate() {
+                parent.spawn((
+                    ModeOption(i),
+                    TextBundle::from_section(mode.label(), style.clone()),
+                ));
+            }
+
+            parent.spawn((SlotLabel, TextBundle::from_section("", style.clone())));
+
+            parent.spawn(TextBundle::from_section(
+                "Left/Right: save slot",
+                TextStyle {
+                    color: HINT_COLOR,
+                    ..style.clone()
+                },
+            ));
+        });
+}
+
+fn read_menu_input(
+    mut commands: Commands,
+    mut menu: ResMut<CreateGameMenu>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || gamepad.back {
+        next_state.set(AppState::MainMenu);
+    } else if keys.any_just_pressed([KeyCode::Space, KeyCode::Enter]) || gamepad.confirm {
+        sounds.send(PlaySound(Sfx::UiSelect));
+
+        commands.insert_resource(Campaign {
+            slot: Some(menu.slot),
+            progress: menu.slots[menu.slot].clone(),
+        });
+
+        match GameMode::ALL[menu.selected] {
+            GameMode::Online => {
+                let config = GameConfig::from_settings(&settings);
+                commands.insert_resource(CMRole::ConfigBearer(config));
+                next_state.set(AppState::WaitingInLobby);
+            }
+            GameMode::CouchCoop => {
+                commands.insert_resource(LocalPlayerCount(2));
+                next_state.set(AppState::LocalLobby);
+            }
+            GameMode::Solo => {
+                commands.insert_resource(LocalPlayerCount(1));
+                next_state.set(AppState::LocalLobby);
+            }
+        }
+    } else if keys.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) || gamepad.left {
+        menu.slot = menu.slot.checked_sub(1).unwrap_or(SAVE_SLOTS - 1);
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) || gamepad.right {
+        menu.slot = (menu.slot + 1) % SAVE_SLOTS;
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) || gamepad.previous {
+        menu.selected = menu
+            .selected
+            .checked_sub(1)
+            .unwrap_or(GameMode::ALL.len() - 1);
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) || gamepad.next {
+        menu.selected = (menu.selected + 1) % GameMode::ALL.len();
+        sounds.send(PlaySound(Sfx::UiMove));
+    }
+}
+
+fn update_options(menu: Res<CreateGameMenu>, mut options: Query<(&ModeOption, &mut Text)>) {
+    for (option, mut text) in &mut options {
+        text.sections[0].style.color = if option.0 == menu.selected {
+            SELECTED_COLOR
+        } else {
+            IDLE_COLOR
+        };
+    }
+}
+
+fn update_slot_label(menu: Res<CreateGameMenu>, mut labels: Query<&mut Text, With<SlotLabel>>) {
+    let progress = &menu.slots[menu.slot];
+
+    let summary = match (progress.levels_cleared(), progress.collectibles_found()) {
+        (0, 0) => "new".to_string(),
+        (cleared, 0) => format!("{cleared} cleared"),
+        (cleared, found) => format!("{cleared} cleared, {found} found"),
+    };
+
+    for mut text in &mut labels {
+        text.sections[0].value = format!("< Slot {} ({summary}) >", menu.slot + 1);
+    }
+}
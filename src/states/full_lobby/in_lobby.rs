@@ -11,10 +11,14 @@ impl Plugin for InLobbyPlugin {
         app.add_systems(
             GgrsSchedule,
             (
+                crate::input::update_input_history,
                 crate::components::handle_coyote_time,
                 crate::movement::move_player_multiplayer,
                 crate::movement::reset,
                 crate::interactions::handle_vine_interactions,
+                crate::interactions::handle_door_triggers,
+                crate::movement::move_platforms,
+                crate::movement::carry_riders,
             )
                 .chain()
                 .in_set(InLobbySet::Update)
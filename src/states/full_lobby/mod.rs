@@ -1,20 +1,50 @@
 pub mod in_lobby;
 
 use bevy::prelude::*;
-use bevy_ggrs::Session;
+use bevy_ggrs::{GgrsApp, GgrsSchedule, Session};
 
-use crate::{AppState, Config, DespawnAllButCameraID, MultiplayerGameState};
+use crate::{
+    components::{Door, Player},
+    history::{count_cleared_levels, finish_match, start_match, CurrentMatch, EndReason},
+    input::GamepadMenuInput,
+    level::LOBBY_LEVEL,
+    replay::WatchedReplay,
+    rollback::{
+        release_confirmed_events, ConfirmedEventApp, ConfirmedEvents, RollbackFrame, ROLLBACK_FPS,
+    },
+    save::record_level_clears,
+    states::full_lobby::in_lobby::InLobbySet,
+    AppState, Config, DespawnAllButCameraID, MultiplayerGameState,
+};
 
 pub struct FullLobbyPlugin;
 
 impl Plugin for FullLobbyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::FullLobby), start_ggrs_state)
-            .add_systems(
-                Update,
-                handle_ggrs_events.run_if(in_state(AppState::FullLobby)),
+        app.add_systems(
+            OnEnter(AppState::FullLobby),
+            (start_ggrs_state, start_match, start_level_clock),
+        )
+        .add_systems(
+            Update,
+            (
+                handle_ggrs_events,
+                leave_local_session.run_if(in_local_session),
+                (count_cleared_levels, record_level_clears)
+                    .after(release_confirmed_events::<LevelCleared>),
             )
-            .add_plugins(in_lobby::InLobbyPlugin);
+                .run_if(in_state(AppState::FullLobby)),
+        )
+        .add_systems(
+            GgrsSchedule,
+            detect_level_clears
+                .after(InLobbySet::Update)
+                .run_if(in_state(MultiplayerGameState::InLobby)),
+        )
+        .rollback_resource_with_copy::<LevelClock>()
+        .add_confirmed_event::<LevelCleared>()
+        .add_systems(OnExit(AppState::FullLobby), finish_match)
+        .add_plugins(in_lobby::InLobbyPlugin);
     }
 }
 
@@ -23,9 +53,62 @@ fn start_ggrs_state(mut multiplayer_state: ResMut<NextState<MultiplayerGameState
     info!("Starting multiplayer schedule");
 }
 
+/// sent once every player makes it into the door and no rollback can take that back
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LevelCleared {
+    pub level: u32,
+    /// seconds since the level started, counted in rollback frames so both peers agree
+    pub time: f32,
+}
+
+/// rolled back with the game so a clear that gets resimulated is timed the same way again
+#[derive(Resource, Debug, Clone, Copy)]
+struct LevelClock {
+    /// rollback frame the current level started on
+    started: u32,
+    /// whether every player was in the door last frame, so a level only counts once per visit
+    door_full: bool,
+}
+
+fn start_level_clock(mut commands: Commands, frame: Res<RollbackFrame>) {
+    commands.insert_resource(LevelClock {
+        started: frame.0,
+        door_full: false,
+    });
+}
+
+fn detect_level_clears(
+    doors: Query<&Door>,
+    players: Query<(), With<Player>>,
+    frame: Res<RollbackFrame>,
+    mut clock: ResMut<LevelClock>,
+    mut clears: ResMut<ConfirmedEvents<LevelCleared>>,
+) {
+    let player_count = players.iter().count();
+    let door_full = doors
+        .iter()
+        .any(|door| door.occupants as usize >= player_count);
+
+    if door_full && !clock.door_full {
+        let frames = frame.0 - clock.started;
+
+        clears.send(
+            frame.0,
+            LevelCleared {
+                level: LOBBY_LEVEL,
+                time: frames as f32 / ROLLBACK_FPS as f32,
+            },
+        );
+        clock.started = frame.0;
+    }
+
+    clock.door_full = door_full;
+}
+
 fn handle_ggrs_events(
     mut commands: Commands,
     mut session: ResMut<Session<Config>>,
+    mut current_match: ResMut<CurrentMatch>,
     abc_id: Res<DespawnAllButCameraID>,
     mut next_state: ResMut<NextState<AppState>>,
     mut multiplayer_state: ResMut<NextState<MultiplayerGameState>>,
@@ -36,6 +119,7 @@ fn handle_ggrs_events(
                 match event {
                     bevy_ggrs::ggrs::GgrsEvent::Disconnected { .. } => {
                         error!("Disconnect, sending back to main menu");
+                        current_match.set_end_reason(EndReason::Disconnected);
 
                         commands.run_system(abc_id.0);
                         next_state.set(AppState::MainMenu);
@@ -56,3 +140,31 @@ fn handle_ggrs_events(
         _ => {}
     }
 }
+
+fn in_local_session(session: Res<Session<Config>>) -> bool {
+    matches!(session.as_ref(), Session::SyncTest(_))
+}
+
+/// local sessions have no peer to disconnect, so leaving is up to the players, or a replay
+/// running out
+fn leave_local_session(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
+    replay: WatchedReplay,
+    abc_id: Res<DespawnAllButCameraID>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut multiplayer_state: ResMut<NextState<MultiplayerGameState>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || gamepad.back || replay.is_over() {
+        info!("Leaving local session");
+
+        commands.run_system(abc_id.0);
+        // a replay goes back to the list it was picked from
+        next_state.set(match replay.is_watching() {
+            true => AppState::PastGamesMenu,
+            false => AppState::MainMenu,
+        });
+        multiplayer_state.set(MultiplayerGameState::Idle);
+    }
+}
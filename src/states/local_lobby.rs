@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use bevy_ggrs::{ggrs, GgrsTime, Session};
+
+use crate::{
+    args::Args,
+    despawn_all_but_camera,
+    replay::ReplayPlayback,
+    settings::Settings,
+    states::waiting_lobby::{
+        spawn_background, spawn_doors, spawn_hills, spawn_moving_platforms, spawn_platforms,
+        spawn_player, spawn_vines, GameConfig,
+    },
+    AppState, Config,
+};
+
+/// how many frames back a synctest session resimulates to look for desyncs when the game is
+/// started with `--synctest`
+const SYNCTEST_CHECK_DISTANCE: usize = 2;
+
+pub struct LocalLobbyPlugin;
+
+impl Plugin for LocalLobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::LocalLobby),
+            (
+                despawn_all_but_camera,
+                spawn_background,
+                spawn_platforms,
+                spawn_hills,
+                spawn_moving_platforms,
+                spawn_player,
+                spawn_vines,
+                spawn_doors,
+                start_local_session,
+            )
+                .chain()
+                .in_set(LocalLobbySet::Setup),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LocalLobbySet {
+    Setup,
+}
+
+/// how many players share this machine, 1 for solo practice and 2 for couch co-op
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalPlayerCount(pub usize);
+
+/// runs every player on this machine in a synctest session, which needs no socket and treats
+/// every player as local, it only checks for desyncs when `--synctest` is passed
+///
+/// a replay being watched brings its own config, and never rolls back so its inputs can be read
+/// by frame
+fn start_local_session(
+    mut commands: Commands,
+    args: Res<Args>,
+    settings: Res<Settings>,
+    player_count: Res<LocalPlayerCount>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let check_distance = match args.synctest && playback.is_none() {
+        true => SYNCTEST_CHECK_DISTANCE,
+        false => 0,
+    };
+
+    let config = match &playback {
+        Some(playback) => playback.0.config,
+        None => GameConfig::from_settings(&settings),
+    };
+
+    let session = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(player_count.0)
+        .with_check_distance(check_distance)
+        .start_synctest_session()
+        .expect("failed to start local session");
+
+    commands.insert_resource(Time::new_with(GgrsTime));
+    commands.insert_resource(Session::SyncTest(session));
+    commands.insert_resource(config);
+
+    info!("Started local session, entering full lobby");
+    next_state.set(AppState::FullLobby);
+}
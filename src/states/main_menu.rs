@@ -2,7 +2,12 @@ use std::{cmp::Ordering, time::Duration};
 
 use bevy::prelude::*;
 
-use crate::{despawn_all_but_camera, AppState};
+use crate::{
+    audio::{PlaySound, Sfx},
+    despawn_all_but_camera,
+    input::GamepadMenuInput,
+    AppState,
+};
 
 const MENU_BUTTONS: usize = 4;
 const MENU_BUTTON_MOVE_TIME: f32 = 0.25;
@@ -72,8 +77,8 @@ impl MainMenuManager {
             //TODO: change
             0 => Some(AppState::CreateGameMenu),
             1 => Some(AppState::JoinGameMenu),
-            2 => None,
-            3 => None,
+            2 => Some(AppState::PastGamesMenu),
+            3 => Some(AppState::SettingsMenu),
             _ => None,
         }
     }
@@ -283,15 +288,20 @@ fn handle_buttons(
 fn read_keyboard(
     mut manager: ResMut<MainMenuManager>,
     keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
     mut next_state: ResMut<NextState<AppState>>,
+    mut sounds: EventWriter<PlaySound>,
 ) {
-    if keys.any_pressed([KeyCode::Space, KeyCode::Enter]) {
+    if keys.any_pressed([KeyCode::Space, KeyCode::Enter]) || gamepad.confirm {
         if let Some(state) = manager.get_state_for_selected() {
             next_state.set(state);
+            sounds.send(PlaySound(Sfx::UiSelect));
         }
-    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::KeyA]) {
+    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::KeyA]) || gamepad.previous {
         manager.decrement();
-    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::KeyD]) {
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::KeyD]) || gamepad.next {
         manager.increment();
+        sounds.send(PlaySound(Sfx::UiMove));
     }
 }
@@ -1,3 +1,7 @@
+pub mod create_game_menu;
 pub mod full_lobby;
+pub mod local_lobby;
 pub mod main_menu;
+pub mod past_games_menu;
+pub mod settings_menu;
 pub mod waiting_lobby;
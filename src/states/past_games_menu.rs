@@ -0,0 +1,266 @@
+use bevy::prelude::*;
+
+use crate::{
+    despawn_all_but_camera,
+    history::{format_date, EndReason, MatchMode, MatchRecord},
+    input::GamepadMenuInput,
+    replay::{Replay, ReplayPlayback},
+    states::local_lobby::LocalPlayerCount,
+    AppState,
+};
+
+const FONT_SIZE: f32 = 8.;
+const SELECTED_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+const IDLE_COLOR: Color = Color::WHITE;
+const HINT_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+const DISCONNECTED_COLOR: Color = Color::srgb(1., 0.5, 0.5);
+
+/// how many games fit on screen at once
+const VISIBLE_ROWS: usize = 12;
+
+pub struct PastGamesMenuPlugin;
+
+impl Plugin for PastGamesMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::PastGamesMenu),
+            (despawn_all_but_camera, setup)
+                .chain()
+                .in_set(PastGamesMenuSet::Setup),
+        )
+        .add_systems(
+            Update,
+            (read_menu_input, update_rows)
+                .chain()
+                .in_set(PastGamesMenuSet::Update),
+        )
+        .configure_sets(
+            Update,
+            PastGamesMenuSet::Update.run_if(in_state(AppState::PastGamesMenu)),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PastGamesMenuSet {
+    Setup,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFilter {
+    All,
+    Online,
+    Local,
+    Disconnected,
+}
+
+impl HistoryFilter {
+    const ALL: [HistoryFilter; 4] = [
+        HistoryFilter::All,
+        HistoryFilter::Online,
+        HistoryFilter::Local,
+        HistoryFilter::Disconnected,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            HistoryFilter::All => "All games",
+            HistoryFilter::Online => "Online",
+            HistoryFilter::Local => "Local",
+            HistoryFilter::Disconnected => "Disconnected",
+        }
+    }
+
+    fn matches(&self, record: &MatchRecord) -> bool {
+        match self {
+            HistoryFilter::All => true,
+            HistoryFilter::Online => record.mode == MatchMode::Online,
+            HistoryFilter::Local => record.mode != MatchMode::Online,
+            HistoryFilter::Disconnected => record.end_reason == EndReason::Disconnected,
+        }
+    }
+}
+
+#[derive(Debug, Resource)]
+struct PastGames {
+    /// newest first
+    records: Vec<MatchRecord>,
+    filter: usize,
+    /// picked game, counted among the filtered ones
+    selected: usize,
+    /// how many filtered games are above the first row
+    scroll: usize,
+}
+
+impl PastGames {
+    fn filter(&self) -> HistoryFilter {
+        HistoryFilter::ALL[self.filter]
+    }
+
+    fn filtered(&self) -> impl Iterator<Item = &MatchRecord> {
+        let filter = self.filter();
+        self.records
+            .iter()
+            .filter(move |record| filter.matches(record))
+    }
+}
+
+#[derive(Component, Debug)]
+struct FilterLabel;
+
+/// one line of the list, showing the game this many rows below the scroll position
+#[derive(Component, Debug)]
+struct HistoryRow(usize);
+
+fn setup(mut commands: Commands) {
+    let mut records = MatchRecord::load_all();
+    records.reverse();
+
+    commands.insert_resource(PastGames {
+        records,
+        filter: 0,
+        selected: 0,
+        scroll: 0,
+    });
+
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: IDLE_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(6.)),
+                row_gap: Val::Px(2.),
+                ..default()
+            },
+            background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((FilterLabel, TextBundle::from_section("", style.clone())));
+
+            for i in 0..VISIBLE_ROWS {
+                parent.spawn((HistoryRow(i), TextBundle::from_section("", style.clone())));
+            }
+
+            parent.spawn(TextBundle::from_section(
+                "Enter: watch replay  Left/Right: filter  Esc: back",
+                TextStyle {
+                    color: HINT_COLOR,
+                    ..style.clone()
+                },
+            ));
+        });
+}
+
+fn read_menu_input(
+    mut commands: Commands,
+    mut past_games: ResMut<PastGames>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let shown = past_games.filtered().count();
+
+    if keys.just_pressed(KeyCode::Escape) || gamepad.back {
+        next_state.set(AppState::MainMenu);
+    } else if keys.any_just_pressed([KeyCode::Space, KeyCode::Enter]) || gamepad.confirm {
+        let Some(record) = past_games.filtered().nth(past_games.selected) else {
+            return;
+        };
+
+        // games from before replays were recorded have nothing to watch
+        let Some(replay) = record.replay.as_deref().and_then(Replay::load) else {
+            return;
+        };
+
+        info!(
+            "Watching the replay of the game from {}",
+            format_date(record.date)
+        );
+
+        commands.insert_resource(LocalPlayerCount(replay.players));
+        commands.insert_resource(ReplayPlayback(replay));
+        next_state.set(AppState::LocalLobby);
+    } else if keys.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) || gamepad.left {
+        past_games.filter = past_games
+            .filter
+            .checked_sub(1)
+            .unwrap_or(HistoryFilter::ALL.len() - 1);
+        past_games.selected = 0;
+        past_games.scroll = 0;
+    } else if keys.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) || gamepad.right {
+        past_games.filter = (past_games.filter + 1) % HistoryFilter::ALL.len();
+        past_games.selected = 0;
+        past_games.scroll = 0;
+    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) || gamepad.previous {
+        past_games.selected = past_games.selected.saturating_sub(1);
+        past_games.scroll = past_games.scroll.min(past_games.selected);
+    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) || gamepad.next {
+        past_games.selected = (past_games.selected + 1).min(shown.saturating_sub(1));
+        past_games.scroll = past_games
+            .scroll
+            .max((past_games.selected + 1).saturating_sub(VISIBLE_ROWS));
+    }
+}
+
+fn update_rows(
+    past_games: Res<PastGames>,
+    mut label: Query<&mut Text, (With<FilterLabel>, Without<HistoryRow>)>,
+    mut rows: Query<(&HistoryRow, &mut Text), Without<FilterLabel>>,
+) {
+    if !past_games.is_changed() {
+        return;
+    }
+
+    let shown = past_games.filtered().count();
+
+    for mut text in &mut label {
+        text.sections[0].value = format!("< {} ({shown}) >", past_games.filter().label());
+    }
+
+    for (row, mut text) in &mut rows {
+        let index = past_games.scroll + row.0;
+        let record = past_games.filtered().nth(index);
+
+        let (value, color) = match record {
+            Some(record) => (
+                describe(record),
+                match record.end_reason {
+                    _ if index == past_games.selected => SELECTED_COLOR,
+                    EndReason::Disconnected => DISCONNECTED_COLOR,
+                    EndReason::Left => IDLE_COLOR,
+                },
+            ),
+            None if row.0 == 0 && shown == 0 => ("No games yet".to_string(), HINT_COLOR),
+            None => (String::new(), IDLE_COLOR),
+        };
+
+        text.sections[0].value = value;
+        text.sections[0].style.color = color;
+    }
+}
+
+/// one line summary, date, who with, levels cleared and how long it lasted
+fn describe(record: &MatchRecord) -> String {
+    let partner = match record.mode {
+        MatchMode::Online => record.partner.as_deref().unwrap_or("?"),
+        mode => mode.label(),
+    };
+
+    let minutes = (record.duration / 60.) as u32;
+    let seconds = (record.duration % 60.) as u32;
+
+    format!(
+        "{} {partner:<8.8} L{} {minutes}:{seconds:02}",
+        format_date(record.date),
+        record.levels_cleared,
+    )
+}
@@ -0,0 +1,578 @@
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+
+use crate::{
+    audio::{PlaySound, Sfx},
+    despawn_all_but_camera,
+    input::{Action, GamepadMenuInput, InputMap, Keyboard, BINDING_SLOTS},
+    resources::WindowScale,
+    settings::{ScaleSnap, Settings, MAX_DISPLAY_NAME_LEN, MAX_INPUT_DELAY},
+    AppState,
+};
+
+const FONT_SIZE: f32 = 8.;
+const SELECTED_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+const IDLE_COLOR: Color = Color::WHITE;
+const HINT_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+
+const VOLUME_STEP: f32 = 0.1;
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::SettingsMenu),
+            (despawn_all_but_camera, setup)
+                .chain()
+                .in_set(SettingsMenuSet::Setup),
+        )
+        .add_systems(
+            Update,
+            (
+                edit_text_setting,
+                read_settings_input,
+                read_rebind_input,
+                update_pages,
+                update_setting_rows,
+                update_binding_rows,
+            )
+                .chain()
+                .in_set(SettingsMenuSet::Update),
+        )
+        .configure_sets(
+            Update,
+            SettingsMenuSet::Update.run_if(in_state(AppState::SettingsMenu)),
+        );
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SettingsMenuSet {
+    Setup,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    General,
+    Controls,
+}
+
+#[derive(Debug, Resource, Clone)]
+struct SettingsMenu {
+    page: Page,
+    selected: usize,
+    /// binding slot of the selected action, the key slots come first and then the gamepad ones
+    slot: usize,
+    /// whose keys the controls page shows, playing alone or one of the couch players
+    keyboard: Keyboard,
+    /// waiting for a key or button to bind to the selected slot
+    rebinding: bool,
+    /// what's been typed so far into the selected text setting
+    editing: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    WindowScale,
+    Fullscreen,
+    ScaleSnap,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    InputDelay,
+    SignalingServer,
+    DisplayName,
+    SplitScreen,
+    PlayerCollisions,
+    Controls,
+}
+
+impl Setting {
+    const ALL: [Setting; 12] = [
+        Setting::WindowScale,
+        Setting::Fullscreen,
+        Setting::ScaleSnap,
+        Setting::MasterVolume,
+        Setting::MusicVolume,
+        Setting::SfxVolume,
+        Setting::InputDelay,
+        Setting::SignalingServer,
+        Setting::DisplayName,
+        Setting::SplitScreen,
+        Setting::PlayerCollisions,
+        Setting::Controls,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Setting::WindowScale => "Scale",
+            Setting::Fullscreen => "Screen",
+            Setting::ScaleSnap => "Snap",
+            Setting::MasterVolume => "Volume",
+            Setting::MusicVolume => "Music",
+            Setting::SfxVolume => "Effects",
+            Setting::InputDelay => "Delay",
+            Setting::SignalingServer => "Server",
+            Setting::DisplayName => "Name",
+            Setting::SplitScreen => "Split",
+            Setting::PlayerCollisions => "Collide",
+            Setting::Controls => "Controls",
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct GeneralPage;
+
+#[derive(Component, Debug)]
+struct ControlsPage;
+
+/// title of the controls page, naming the keyboard layout being edited
+#[derive(Component, Debug)]
+struct ControlsTitle;
+
+/// one line of the general settings, showing the setting at this index of `Setting::ALL`
+#[derive(Component, Debug)]
+struct SettingRow(usize);
+
+/// one line of the bindings list, showing the action at this index of `Action::ALL`
+#[derive(Component, Debug)]
+struct BindingRow(usize);
+
+fn setup(mut commands: Commands) {
+    commands.insert_resource(SettingsMenu {
+        page: Page::General,
+        selected: 0,
+        slot: 0,
+        keyboard: Keyboard::Solo,
+        rebinding: false,
+        editing: None,
+    });
+
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: IDLE_COLOR,
+        ..default()
+    };
+
+    let hint_style = TextStyle {
+        color: HINT_COLOR,
+        ..style.clone()
+    };
+
+    let page_style = Style {
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        flex_direction: FlexDirection::Column,
+        padding: UiRect::all(Val::Px(6.)),
+        row_gap: Val::Px(2.),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    GeneralPage,
+                    NodeBundle {
+                        style: page_style.clone(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Settings", style.clone()));
+
+                    for i in 0..Setting::ALL.len() {
+                        parent.spawn((SettingRow(i), TextBundle::from_section("", style.clone())));
+                    }
+
+                    parent.spawn(TextBundle::from_section(
+                        "Left/Right: change  Enter: edit  Esc: back",
+                        hint_style.clone(),
+                    ));
+                });
+
+            parent
+                .spawn((
+                    ControlsPage,
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            ..page_style.clone()
+                        },
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((ControlsTitle, TextBundle::from_section("", style.clone())));
+
+                    for i in 0..Action::ALL.len() {
+                        parent.spawn((BindingRow(i), TextBundle::from_section("", style.clone())));
+                    }
+
+                    parent.spawn(TextBundle::from_section(
+                        "Left/Right: slot  Tab: layout  Enter: rebind  R: reset  Esc: back",
+                        hint_style.clone(),
+                    ));
+                });
+        });
+}
+
+/// typing into the server address or display name, Enter keeps the text and Esc throws it away
+fn edit_text_setting(
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+) {
+    // events are always read so keys typed before editing started don't show up in the text
+    let events = keyboard_events
+        .read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.logical_key.clone())
+        .collect::<Vec<_>>();
+
+    if menu.editing.is_none() {
+        return;
+    }
+
+    let setting = Setting::ALL[menu.selected];
+    let mut text = menu.editing.take().unwrap_or_default();
+
+    for key in events {
+        match key {
+            Key::Character(c) => text.extend(c.chars().filter(|c| !c.is_control())),
+            Key::Space => text.push(' '),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Enter => {
+                match setting {
+                    Setting::SignalingServer => settings.signaling_server = text.trim().into(),
+                    _ => settings.display_name = text.trim().into(),
+                }
+
+                settings.save();
+                return;
+            }
+            Key::Escape => return,
+            _ => {}
+        }
+    }
+
+    if setting == Setting::DisplayName {
+        text.truncate(
+            text.char_indices()
+                .nth(MAX_DISPLAY_NAME_LEN)
+                .map_or(text.len(), |(i, _)| i),
+        );
+    }
+
+    menu.editing = Some(text);
+}
+
+fn read_settings_input(
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut window_scale: ResMut<WindowScale>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    // the Enter or Esc that finished typing or closed the controls page was already handled
+    if menu.page != Page::General || menu.editing.is_some() || menu.is_changed() {
+        return;
+    }
+
+    let setting = Setting::ALL[menu.selected];
+
+    let change = if keys.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) || gamepad.left {
+        -1
+    } else if keys.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) || gamepad.right {
+        1
+    } else {
+        0
+    };
+
+    if change != 0 {
+        match setting {
+            Setting::WindowScale if change < 0 => {
+                window_scale.decrease();
+            }
+            Setting::WindowScale => {
+                window_scale.increase();
+            }
+            Setting::MasterVolume => step_volume(&mut settings.master_volume, change),
+            Setting::MusicVolume => step_volume(&mut settings.music_volume, change),
+            Setting::SfxVolume => step_volume(&mut settings.sfx_volume, change),
+            Setting::InputDelay => {
+                settings.input_delay = settings
+                    .input_delay
+                    .saturating_add_signed(change)
+                    .min(MAX_INPUT_DELAY);
+            }
+            Setting::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Setting::ScaleSnap => {
+                settings.scale_snap = match settings.scale_snap {
+                    ScaleSnap::Integer => ScaleSnap::Fit,
+                    ScaleSnap::Fit => ScaleSnap::Integer,
+                };
+            }
+            Setting::SplitScreen => settings.split_screen = !settings.split_screen,
+            Setting::PlayerCollisions => {
+                settings.player_collisions = !settings.player_collisions;
+            }
+            _ => return,
+        }
+
+        // the window scale is saved once the window has actually been resized
+        if setting != Setting::WindowScale {
+            settings.save();
+        }
+
+        // played at the new volume, so the effects slider can be heard as it moves
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.just_pressed(KeyCode::Escape) || gamepad.back {
+        next_state.set(AppState::MainMenu);
+    } else if keys.just_pressed(KeyCode::Enter) || gamepad.confirm {
+        sounds.send(PlaySound(Sfx::UiSelect));
+
+        if setting == Setting::SignalingServer {
+            menu.editing = Some(settings.signaling_server.clone());
+        } else if setting == Setting::DisplayName {
+            menu.editing = Some(settings.display_name.clone());
+        } else if setting == Setting::Controls {
+            menu.page = Page::Controls;
+            menu.selected = 0;
+            menu.slot = 0;
+            menu.keyboard = Keyboard::Solo;
+        }
+    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) || gamepad.previous {
+        menu.selected = menu
+            .selected
+            .checked_sub(1)
+            .unwrap_or(Setting::ALL.len() - 1);
+        sounds.send(PlaySound(Sfx::UiMove));
+    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) || gamepad.next {
+        menu.selected = (menu.selected + 1) % Setting::ALL.len();
+        sounds.send(PlaySound(Sfx::UiMove));
+    }
+}
+
+/// moves a volume by one step, rounding so repeated steps don't drift away from tenths
+fn step_volume(volume: &mut f32, change: isize) {
+    let steps = (*volume / VOLUME_STEP).round() + change as f32;
+    *volume = (steps * VOLUME_STEP).clamp(0., 1.);
+}
+
+fn read_rebind_input(
+    mut menu: ResMut<SettingsMenu>,
+    mut input_map: ResMut<InputMap>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad: Res<GamepadMenuInput>,
+) {
+    // the Enter that opened this page was already handled
+    if menu.page != Page::Controls || menu.is_changed() {
+        return;
+    }
+
+    let action = Action::ALL[menu.selected];
+
+    if menu.rebinding {
+        // key slots only take keys and gamepad slots only take buttons
+        let slot = menu.slot;
+
+        if keys.just_pressed(KeyCode::Escape) {
+            menu.rebinding = false;
+        } else if slot < BINDING_SLOTS {
+            if let Some(key) = keys.get_just_pressed().next() {
+                input_map.bind_key(menu.keyboard, action, slot, *key);
+                input_map.save();
+                menu.rebinding = false;
+            }
+        } else if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+            input_map.bind_gamepad_button(action, slot - BINDING_SLOTS, button.button_type);
+            input_map.save();
+            menu.rebinding = false;
+        }
+
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) || gamepad.back {
+        menu.page = Page::General;
+        menu.selected = Setting::ALL.len() - 1;
+    } else if keys.just_pressed(KeyCode::Enter) || gamepad.confirm {
+        menu.rebinding = true;
+    } else if keys.just_pressed(KeyCode::Tab) {
+        let next = Keyboard::ALL
+            .iter()
+            .position(|keyboard| *keyboard == menu.keyboard)
+            .map_or(0, |i| (i + 1) % Keyboard::ALL.len());
+        menu.keyboard = Keyboard::ALL[next];
+    } else if keys.just_pressed(KeyCode::KeyR) {
+        *input_map = InputMap::default();
+        input_map.save();
+    } else if keys.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) || gamepad.left {
+        menu.slot = menu.slot.checked_sub(1).unwrap_or(BINDING_SLOTS * 2 - 1);
+    } else if keys.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) || gamepad.right {
+        menu.slot = (menu.slot + 1) % (BINDING_SLOTS * 2);
+    } else if keys.any_just_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) || gamepad.previous {
+        menu.selected = menu
+            .selected
+            .checked_sub(1)
+            .unwrap_or(Action::ALL.len() - 1);
+    } else if keys.any_just_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) || gamepad.next {
+        menu.selected = (menu.selected + 1) % Action::ALL.len();
+    }
+}
+
+fn update_pages(
+    menu: Res<SettingsMenu>,
+    mut general: Query<&mut Style, (With<GeneralPage>, Without<ControlsPage>)>,
+    mut controls: Query<&mut Style, (With<ControlsPage>, Without<GeneralPage>)>,
+) {
+    let (general_display, controls_display) = match menu.page {
+        Page::General => (Display::Flex, Display::None),
+        Page::Controls => (Display::None, Display::Flex),
+    };
+
+    for mut style in &mut general {
+        style.display = general_display;
+    }
+
+    for mut style in &mut controls {
+        style.display = controls_display;
+    }
+}
+
+fn update_setting_rows(
+    menu: Res<SettingsMenu>,
+    settings: Res<Settings>,
+    window_scale: Res<WindowScale>,
+    mut rows: Query<(&SettingRow, &mut Text)>,
+) {
+    for (row, mut text) in &mut rows {
+        let setting = Setting::ALL[row.0];
+        let selected = menu.page == Page::General && row.0 == menu.selected;
+
+        let value = match setting {
+            Setting::WindowScale => format!("{}x", window_scale.0),
+            Setting::Fullscreen => match settings.fullscreen {
+                true => "Full".into(),
+                false => "Window".into(),
+            },
+            Setting::ScaleSnap => match settings.scale_snap {
+                ScaleSnap::Integer => "Whole".into(),
+                ScaleSnap::Fit => "Fit".into(),
+            },
+            Setting::MasterVolume => volume_percent(settings.master_volume),
+            Setting::MusicVolume => volume_percent(settings.music_volume),
+            Setting::SfxVolume => volume_percent(settings.sfx_volume),
+            Setting::InputDelay => format!("{} frames", settings.input_delay),
+            Setting::SignalingServer => settings.signaling_server.clone(),
+            Setting::DisplayName => settings.display_name.clone(),
+            Setting::SplitScreen => match settings.split_screen {
+                true => "On".into(),
+                false => "Off".into(),
+            },
+            Setting::PlayerCollisions => match settings.player_collisions {
+                true => "On".into(),
+                false => "Off".into(),
+            },
+            Setting::Controls => ">".into(),
+        };
+
+        let value = match &menu.editing {
+            Some(text) if selected => format!("{text}_"),
+            _ => value,
+        };
+
+        text.sections[0].value = format!("{:<9}{value}", setting.name());
+        text.sections[0].style.color = if selected { SELECTED_COLOR } else { IDLE_COLOR };
+    }
+}
+
+fn volume_percent(volume: f32) -> String {
+    format!("{}%", (volume * 100.).round())
+}
+
+fn update_binding_rows(
+    menu: Res<SettingsMenu>,
+    input_map: Res<InputMap>,
+    mut rows: Query<(&BindingRow, &mut Text), Without<ControlsTitle>>,
+    mut title: Query<&mut Text, With<ControlsTitle>>,
+) {
+    for mut text in &mut title {
+        text.sections[0].value = format!("Controls: {}", menu.keyboard.name());
+    }
+
+    for (row, mut text) in &mut rows {
+        let action = Action::ALL[row.0];
+        let selected = menu.page == Page::Controls && row.0 == menu.selected;
+
+        let keys = (0..BINDING_SLOTS).map(|slot| {
+            input_map
+                .keys(menu.keyboard, action)
+                .get(slot)
+                .map(|key| key_name(*key))
+        });
+        let buttons = (0..BINDING_SLOTS).map(|slot| {
+            input_map
+                .gamepad_buttons(action)
+                .get(slot)
+                .map(|button| format!("{button:?}"))
+        });
+
+        let slots = keys
+            .chain(buttons)
+            .enumerate()
+            .map(|(slot, binding)| {
+                let binding = binding.unwrap_or_else(|| "-".into());
+
+                match selected && slot == menu.slot {
+                    true if menu.rebinding => "[?]".to_string(),
+                    true => format!("[{binding}]"),
+                    false => binding,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bindings = format!(
+            "{} | {}",
+            slots[..BINDING_SLOTS].join(" "),
+            slots[BINDING_SLOTS..].join(" ")
+        );
+
+        text.sections[0].value = format!("{:<9}{bindings}", action.name());
+        text.sections[0].style.color = if selected { SELECTED_COLOR } else { IDLE_COLOR };
+    }
+}
+
+/// short name for a key, `KeyW` shows as `W` and `ArrowUp` as `Up`
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+
+    ["Key", "Digit", "Arrow"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(&name)
+        .to_string()
+}
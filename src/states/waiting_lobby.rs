@@ -1,13 +1,30 @@
 use std::time::Duration;
 
-use crate::components::{CoyoteTime, Platform, Player, Vine};
-use crate::physics::{Collider, Gravity, PhysicsSet, Solid, Velocity};
+use crate::components::{
+    Climbing, CoyoteTime, Door, InputHistory, JumpBuffer, Jumping, MovementParams, MovingPlatform,
+    Platform, PlatformPath, Player, Vine,
+};
+use crate::physics::{
+    Collider, Gravity, Mass, PhysicsMaterial, PhysicsSet, SlopeAngle, Solid, Velocity, LAYER_ALL,
+    LAYER_PLAYER, LAYER_VINE, LAYER_WORLD,
+};
 use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 use bevy_ggrs::{ggrs, AddRollbackCommandExtension, GgrsTime};
 use bevy_matchbox::prelude::{ChannelConfig, MultipleChannels, PeerId, SingleChannel, WebRtcSocketBuilder};
 use bevy_matchbox::{CloseSocketExt, MatchboxSocket, OpenSocketExt};
 
-use crate::{despawn_all_but_camera, AppState, Config};
+use crate::{
+    animation::CharacterAnimation,
+    camera::LevelBounds,
+    despawn_all_but_camera,
+    input::GamepadMenuInput,
+    level::{LevelRect, Tile, DOOR_HEX, FLOOR_HEX, LOBBY_SIZE, VINE_HEX},
+    save::{Campaign, Progress},
+    settings::{Settings, MAX_DISPLAY_NAME_LEN},
+    states::local_lobby::LocalPlayerCount,
+    AppState, Config,
+};
 
 const BOTTOM_PLATFORM_HEIGHT: f32 = ((196. - 178.) / 196.) * 10.;
 const BOTTOM_PLATFORM_WIDTH: f32 = 10.;
@@ -27,6 +44,68 @@ const TOP_PLATFORM_LENGTH: f32 = ((70.) / 196.) * 10.;
 const TOP_PLATFORM_POS_Y: f32 = (((110. - 93.) / 2. + (196. - 110.)) / 196.) * 10. - 5.;
 const TOP_PLATFORM_POS_X: f32 = (((70.) / 2.) / 196.) * 10. - 5.;
 
+const MOVING_PLATFORM_SIZE: Vec2 = Vec2::new(1., 0.25);
+/// ferries players back and forth across the gap between the vine and the middle platform
+const FERRY_POINTS: [Vec2; 2] = [Vec2::new(-0.9, -2.7), Vec2::new(0.9, -2.7)];
+const FERRY_SPEED: f32 = 0.9;
+/// bobs up and down under the top platform, low enough that riders can't be squashed into it
+const LIFT_ORIGIN: Vec2 = Vec2::new(-4.45, -3.3);
+const LIFT_EXTENT: Vec2 = Vec2::new(0., 0.3);
+const LIFT_PERIOD: f32 = 3.;
+const MOVING_PLATFORM_COLOR: Color = Color::srgb(0.45, 0.32, 0.2);
+
+/// a small hill on the floor under the top platform, steep on the left and gentle on the right
+const HILL_HEIGHT: f32 = 0.5;
+const HILL_PEAK_X: f32 = -3.3;
+const HILL_COLOR: Color = Color::srgb(0.4, 0.5, 0.25);
+const MUSHROOM_RADIUS: f32 = 0.25;
+const MUSHROOM_POS_X: f32 = -1.85;
+/// bounces players that land on it back up
+const MUSHROOM_COLOR: Color = Color::srgb(0.88, 0.25, 0.63);
+
+/// aiden is the heavier of the two, so nat gets shoved further when they walk into each other
+const NAT_MASS: f32 = 1.;
+const AIDEN_MASS: f32 = 1.5;
+
+/// players collide with everything, `GameConfig::player_collisions` drops `LAYER_PLAYER` from this
+/// to let them pass through each other while still standing on platforms
+const PLAYER_MASK: u8 = LAYER_ALL;
+const WORLD_MASK: u8 = LAYER_ALL & !LAYER_WORLD;
+
+const DOOR_HEIGHT: f32 = ((127. - 93.) / 196.) * 10.;
+const DOOR_WIDTH: f32 = ((176. - 148.) / 196.) * 10.;
+const DOOR_POS_Y: f32 = (((127. - 93.) / 2. + (196. - 127.)) / 196.) * 10. - 5.;
+const DOOR_POS_X: f32 = (((176. - 148.) / 2. + 148.) / 196.) * 10. - 5.;
+
+/// the blocks traced from the lobby background, what each one is comes from its colour
+const LOBBY_RECTS: [LevelRect; 5] = [
+    LevelRect {
+        hex: FLOOR_HEX,
+        position: Vec2::new(0., (BOTTOM_PLATFORM_HEIGHT / 2.) - 5.),
+        size: Vec2::new(BOTTOM_PLATFORM_WIDTH, BOTTOM_PLATFORM_HEIGHT),
+    },
+    LevelRect {
+        hex: FLOOR_HEX,
+        position: Vec2::new(MIDDLE_PLATFORM_POS_X, MIDDLE_PLATFORM_POS_Y),
+        size: Vec2::new(MIDDLE_PLATFORM_LENGTH, MIDDLE_PLATFORM_HEIGHT),
+    },
+    LevelRect {
+        hex: FLOOR_HEX,
+        position: Vec2::new(TOP_PLATFORM_POS_X, TOP_PLATFORM_POS_Y),
+        size: Vec2::new(TOP_PLATFORM_LENGTH, TOP_PLATFORM_HEIGHT),
+    },
+    LevelRect {
+        hex: VINE_HEX,
+        position: Vec2::new(VINE_POS_X, VINE_POS_Y),
+        size: Vec2::new(VINE_WIDTH, VINE_HEIGHT),
+    },
+    LevelRect {
+        hex: DOOR_HEX,
+        position: Vec2::new(DOOR_POS_X, DOOR_POS_Y),
+        size: Vec2::new(DOOR_WIDTH, DOOR_HEIGHT),
+    },
+];
+
 const CONNECTION_TIMEOUT: f32 = 10.;
 
 const CONFIG_BEARER: u8 = 0;
@@ -36,6 +115,10 @@ const ROLE: u8 = 0;
 const CONFIG: u8 = 1;
 const OK: u8 = 2;
 
+/// sent right after `ROLE`, bumped whenever the handshake messages change shape so an old build
+/// is turned away instead of misreading what a newer one sends
+const HANDSHAKE_VERSION: u8 = 2;
+
 pub struct WaitingLobbyPlugin;
 
 impl Plugin for WaitingLobbyPlugin {
@@ -46,8 +129,11 @@ impl Plugin for WaitingLobbyPlugin {
                 despawn_all_but_camera,
                 spawn_background,
                 spawn_platforms,
+                spawn_hills,
+                spawn_moving_platforms,
                 spawn_player,
                 spawn_vines,
+                spawn_doors,
                 start_connection_manager,
             )
                 .chain()
@@ -59,6 +145,13 @@ impl Plugin for WaitingLobbyPlugin {
                 .before(PhysicsSet)
                 .in_set(WaitingLobbySet::Update)
                 .run_if(in_state(AppState::WaitingInLobby)),
+        )
+        // before the first rollback frame, which runs early in the next update
+        .add_systems(
+            Update,
+            apply_player_collisions
+                .after(WaitingLobbySet::Update)
+                .run_if(resource_added::<GameConfig>),
         );
     }
 }
@@ -80,17 +173,38 @@ pub enum CMRole {
     ConfigReciever,
 }
 
-#[derive(Debug, Clone, Copy, Resource)]
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
 pub struct GameConfig {
     pub seed: u32,
     pub difficulty: u32,
+    /// players can't walk through each other, sent after the packed seed and difficulty
+    pub player_collisions: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            seed: 10,
+            difficulty: 1,
+            player_collisions: true,
+        }
+    }
 }
 
 impl GameConfig {
-    pub fn from_u64(data: u64) -> Self {
+    /// the host's game with the choices from their settings
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            player_collisions: settings.player_collisions,
+            ..default()
+        }
+    }
+
+    pub fn from_u64(data: u64, player_collisions: bool) -> Self {
         Self {
             seed: (data & u32::MAX as u64) as u32,
             difficulty: ((data >> 32) & u32::MAX as u64) as u32,
+            player_collisions,
         }
     }
 
@@ -103,6 +217,11 @@ impl GameConfig {
 pub struct ConnectionManager {
     address: String,
     role: CMRole,
+    input_delay: usize,
+    display_name: String,
+    partner_name: Option<String>,
+    /// the campaign being played, sent by the config bearer along with the config
+    progress: Progress,
     state: ConnectionManagerState,
     timeout_timer: Timer,
 }
@@ -116,10 +235,14 @@ enum MetaConnectionState {
 }
 
 impl ConnectionManager {
-    fn new(address: String, room_id: u32, role: CMRole) -> Self {
+    fn new(settings: &Settings, room_id: u32, role: CMRole, progress: Progress) -> Self {
         Self {
-            address: format!("ws://{address}/{room_id}?next=2"),
+            address: format!("ws://{}/{room_id}?next=2", settings.signaling_server),
             role,
+            input_delay: settings.input_delay,
+            display_name: settings.display_name.clone(),
+            partner_name: None,
+            progress,
             state: ConnectionManagerState::PreConnect,
             timeout_timer: Timer::from_seconds(CONNECTION_TIMEOUT, TimerMode::Once),
         }
@@ -182,14 +305,22 @@ impl ConnectionManager {
                     MetaConnectionState::Start => {
                         let channel = matchbox_socket.channel_mut(0);
 
-                        match self.role {
-                            CMRole::ConfigBearer(_) => {
-                                channel.send(Box::new([ROLE, CONFIG_BEARER]), *id)
-                            }
-                            CMRole::ConfigReciever => {
-                                channel.send(Box::new([ROLE, CONFIG_RECEIVER]), *id)
-                            }
-                        }
+                        let role = match self.role {
+                            CMRole::ConfigBearer(_) => CONFIG_BEARER,
+                            CMRole::ConfigReciever => CONFIG_RECEIVER,
+                        };
+
+                        // the display name rides along after the role so the other side can
+                        // show who they played with
+                        channel.send(
+                            [
+                                &[ROLE, HANDSHAKE_VERSION, role][..],
+                                self.display_name.as_bytes(),
+                            ]
+                            .concat()
+                            .into(),
+                            *id,
+                        );
 
                         info!("Connection manager transitioning to waiting on role");
                         self.timeout_timer.reset();
@@ -210,19 +341,49 @@ impl ConnectionManager {
                             return;
                         }
 
+                        let expected_role = match self.role {
+                            CMRole::ConfigBearer(_) => CONFIG_RECEIVER,
+                            CMRole::ConfigReciever => CONFIG_BEARER,
+                        };
+
+                        let message = &messages[0].1;
+
+                        if message
+                            .get(..2)
+                            .is_some_and(|start| start[0] == ROLE && start[1] != HANDSHAKE_VERSION)
+                        {
+                            error!(
+                                "Other player is on handshake version {}, this build speaks {HANDSHAKE_VERSION}",
+                                message[1]
+                            );
+                            self.state = ConnectionManagerState::InvalidConnection;
+                            return;
+                        }
+
+                        if message.get(..3) != Some(&[ROLE, HANDSHAKE_VERSION, expected_role][..]) {
+                            error!("Received invalid meta response");
+                            self.state = ConnectionManagerState::InvalidConnection;
+                            return;
+                        }
+
+                        let partner_name = String::from_utf8_lossy(&message[3..]);
+                        let partner_name = partner_name.trim();
+                        if !partner_name.is_empty() {
+                            self.partner_name =
+                                Some(partner_name.chars().take(MAX_DISPLAY_NAME_LEN).collect());
+                        }
+
                         match self.role {
                             CMRole::ConfigBearer(game_config) => {
-                                #[allow(unused_allocation)]
-                                if messages[0].1 != Box::new([ROLE, CONFIG_RECEIVER]) {
-                                    error!("Received invalid meta response");
-                                    self.state = ConnectionManagerState::InvalidConnection;
-                                    return;
-                                }
-
                                 channel.send(
-                                    [&[CONFIG][..], &game_config.as_u64().to_be_bytes()[..]]
-                                        .concat()
-                                        .into(),
+                                    [
+                                        &[CONFIG][..],
+                                        &game_config.as_u64().to_be_bytes()[..],
+                                        &[u8::from(game_config.player_collisions)][..],
+                                        &self.progress.to_bytes()[..],
+                                    ]
+                                    .concat()
+                                    .into(),
                                     *id,
                                 );
 
@@ -231,13 +392,6 @@ impl ConnectionManager {
                                 *state = MetaConnectionState::WaitingOnOK(game_config);
                             }
                             CMRole::ConfigReciever => {
-                                #[allow(unused_allocation)]
-                                if messages[0].1 != Box::new([ROLE, CONFIG_BEARER]) {
-                                    error!("Received invalid meta response");
-                                    self.state = ConnectionManagerState::InvalidConnection;
-                                    return;
-                                }
-
                                 info!("Connection manager transitioning to waiting on config");
                                 self.timeout_timer.reset();
                                 *state = MetaConnectionState::WaitingOnConfig;
@@ -292,17 +446,29 @@ impl ConnectionManager {
                             return;
                         }
 
-                        let config_value: Result<[u8; 8], _> = messages[0].1[1..].try_into();
-
-                        if config_value.is_err() {
+                        // the 8 byte config and the player collisions flag are followed by the
+                        // bearer's campaign progress
+                        let config_value: Option<[u8; 8]> = messages[0]
+                            .1
+                            .get(1..9)
+                            .and_then(|bytes| bytes.try_into().ok());
+                        let player_collisions = messages[0].1.get(9).map(|flag| *flag != 0);
+                        let progress = messages[0].1.get(10..).and_then(Progress::from_bytes);
+
+                        let (Some(config_value), Some(player_collisions), Some(progress)) =
+                            (config_value, player_collisions, progress)
+                        else {
                             self.state = ConnectionManagerState::InvalidConnection;
                             error!("received invalid meta response while waiting on Config");
                             return;
-                        }
+                        };
 
-                        let config_value = config_value.unwrap();
+                        self.progress = progress;
 
-                        let config = GameConfig::from_u64(u64::from_be_bytes(config_value));
+                        let config = GameConfig::from_u64(
+                            u64::from_be_bytes(config_value),
+                            player_collisions,
+                        );
 
                         channel.send(Box::new([OK]), *id);
 
@@ -323,9 +489,17 @@ impl ConnectionManager {
                     return;
                 }
 
+                // fall back to the peer id when the other side didn't send a name
+                let partner_name = self.partner_name.clone().unwrap_or_else(|| {
+                    matchbox_socket
+                        .connected_peers()
+                        .next()
+                        .map_or_else(String::new, |peer| peer.0.to_string())
+                });
+
                 let mut session_builder = ggrs::SessionBuilder::<Config>::new()
                     .with_num_players(2)
-                    .with_input_delay(2);
+                    .with_input_delay(self.input_delay);
 
                 for (i, player) in players.into_iter().enumerate() {
                     session_builder = session_builder
@@ -343,6 +517,16 @@ impl ConnectionManager {
 
                 commands.insert_resource(bevy_ggrs::Session::P2P(ggrs_session));
                 commands.insert_resource(*game_config);
+                commands.insert_resource(PartnerName(partner_name));
+
+                // the bearer already has its campaign, the receiver plays the bearer's without
+                // saving it
+                if let CMRole::ConfigReciever = self.role {
+                    commands.insert_resource(Campaign {
+                        slot: None,
+                        progress: self.progress.clone(),
+                    });
+                }
 
                 self.state = ConnectionManagerState::Ready;
                 self.timeout_timer.reset();
@@ -370,8 +554,19 @@ pub enum WaitingLobbySet {
 #[derive(Resource, Debug, Clone, Copy)]
 pub struct RoomID(pub u32);
 
-fn start_connection_manager(commands: Commands, room_id: Res<RoomID>, role: Res<CMRole>) {
-    let cm = ConnectionManager::new("3.128.79.14:3536".into(), room_id.0, *role.into_inner());
+/// display name of the peer in an online game, sent with their role during the handshake
+#[derive(Resource, Debug, Clone)]
+pub struct PartnerName(pub String);
+
+fn start_connection_manager(
+    commands: Commands,
+    room_id: Res<RoomID>,
+    role: Res<CMRole>,
+    settings: Res<Settings>,
+    campaign: Option<Res<Campaign>>,
+) {
+    let progress = campaign.map_or_else(Progress::default, |campaign| campaign.progress.clone());
+    let cm = ConnectionManager::new(&settings, room_id.0, *role.into_inner(), progress);
 
     cm.start(commands);
 }
@@ -380,11 +575,12 @@ fn wait_for_players(
     commands: Commands,
     time: Res<Time>,
     keys: Res<ButtonInput<KeyCode>>,
+    gamepad: Res<GamepadMenuInput>,
     mut matchbox_socket: ResMut<MatchboxSocket<MultipleChannels>>,
     mut connection_manager: ResMut<ConnectionManager>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    if keys.just_pressed(KeyCode::Escape) {
+    if keys.just_pressed(KeyCode::Escape) || gamepad.back {
         next_state.set(AppState::MainMenu);
         return;
     }
@@ -404,18 +600,50 @@ fn wait_for_players(
     }
 }
 
-fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// spawns both players, or only the first one when playing solo
+/// players are spawned before an online receiver knows the host's config, so the collision choice
+/// is applied once the config arrives
+fn apply_player_collisions(
+    config: Res<GameConfig>,
+    mut players: Query<&mut Collider, With<Player>>,
+) {
+    let mask = match config.player_collisions {
+        true => PLAYER_MASK,
+        false => PLAYER_MASK & !LAYER_PLAYER,
+    };
+
+    for mut collider in &mut players {
+        collider.set_mask(mask);
+    }
+}
+
+pub(crate) fn spawn_player(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    local_player_count: Option<Res<LocalPlayerCount>>,
+) {
     let player_1_handle: Handle<Image> = asset_server.load("characters/nat.png");
     let player_2_handle: Handle<Image> = asset_server.load("characters/aiden.png");
 
+    let player_1_params = MovementParams::nat();
+    let player_2_params = MovementParams::aiden();
+
     // Player 1
     commands
         .spawn((
             Player { handle: 0 },
-            Gravity(-9.8 * 10., false),
-            CoyoteTime::new(0.125),
-            Collider::new(Vec2::new((1. / 8.167) * 10., (1. / 6.125) * 10.)),
+            Gravity(player_1_params.gravity, false),
+            CoyoteTime::new(player_1_params.coyote_time),
+            JumpBuffer::new(player_1_params.jump_buffer),
+            Jumping::default(),
+            InputHistory::default(),
+            Climbing::default(),
+            CharacterAnimation::new(asset_server.load("animations/nat.anim.ron")),
+            player_1_params,
+            Collider::new(Vec2::new((1. / 8.167) * 10., (1. / 6.125) * 10.))
+                .with_layers(LAYER_PLAYER, PLAYER_MASK),
             Solid(true),
+            Mass(NAT_MASS),
             Velocity::default(),
             SpriteBundle {
                 sprite: Sprite {
@@ -429,14 +657,26 @@ fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
         ))
         .add_rollback();
 
+    if local_player_count.is_some_and(|count| count.0 < 2) {
+        return;
+    }
+
     // Player 2
     commands
         .spawn((
             Player { handle: 1 },
-            Gravity(-9.8 * 10., false),
-            CoyoteTime::new(0.125),
-            Collider::new(Vec2::new((1. / 6.125) * 10., (1. / 6.125) * 10.)),
+            Gravity(player_2_params.gravity, false),
+            CoyoteTime::new(player_2_params.coyote_time),
+            JumpBuffer::new(player_2_params.jump_buffer),
+            Jumping::default(),
+            InputHistory::default(),
+            Climbing::default(),
+            CharacterAnimation::new(asset_server.load("animations/aiden.anim.ron")),
+            player_2_params,
+            Collider::new(Vec2::new((1. / 6.125) * 10., (1. / 6.125) * 10.))
+                .with_layers(LAYER_PLAYER, PLAYER_MASK),
             Solid(true),
+            Mass(AIDEN_MASS),
             Velocity::default(),
             SpriteBundle {
                 transform: Transform::from_translation(Vec3::new(2., 2., 0.)),
@@ -451,68 +691,175 @@ fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
         .add_rollback();
 }
 
-fn spawn_platforms(mut commands: Commands) {
-    commands
-        .spawn((
-            Platform,
-            Collider::new(Vec2::new(BOTTOM_PLATFORM_WIDTH, BOTTOM_PLATFORM_HEIGHT)),
-            Solid(false),
-            Velocity::default(),
-            TransformBundle::from_transform(Transform::from_xyz(
-                0.,
-                (BOTTOM_PLATFORM_HEIGHT / 2.) - 5.,
-                0.,
-            )),
-        ))
-        .add_rollback();
+pub(crate) fn spawn_platforms(mut commands: Commands) {
+    for rect in &LOBBY_RECTS {
+        let Some(Tile::Floor(material)) = rect.tile() else {
+            continue;
+        };
 
-    commands
-        .spawn((
+        let mut platform = commands.spawn((
             Platform,
-            Collider::new(Vec2::new(MIDDLE_PLATFORM_LENGTH, MIDDLE_PLATFORM_HEIGHT)),
+            Collider::new(rect.size).with_layers(LAYER_WORLD, WORLD_MASK),
             Solid(false),
             Velocity::default(),
-            TransformBundle::from_transform(Transform::from_xyz(
-                MIDDLE_PLATFORM_POS_X,
-                MIDDLE_PLATFORM_POS_Y,
-                0.,
-            )),
-        ))
-        .add_rollback();
+            TransformBundle::from_transform(Transform::from_translation(rect.position.extend(0.))),
+        ));
+
+        if let Some(material) = material {
+            platform.insert(material);
+        }
+
+        platform.add_rollback();
+    }
+}
+
+/// ramps and a mushroom to walk over, drawn as flat shapes since the background has no art for
+/// them
+pub(crate) fn spawn_hills(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let floor = BOTTOM_PLATFORM_HEIGHT - 5.;
+    let hill_material = materials.add(HILL_COLOR);
+
+    for (angle, rises_right) in [(SlopeAngle::Deg45, true), (SlopeAngle::Deg22_5, false)] {
+        let width = HILL_HEIGHT / angle.tan();
+        let collider = Collider::slope(width, angle, rises_right);
+        let half = collider.half_size();
+        let x = match rises_right {
+            true => HILL_PEAK_X - half.x,
+            false => HILL_PEAK_X + half.x,
+        };
+        // the tall side of the triangle is on the side it rises towards
+        let tall_x = match rises_right {
+            true => half.x,
+            false => -half.x,
+        };
+
+        commands
+            .spawn((
+                Platform,
+                collider.with_layers(LAYER_WORLD, WORLD_MASK),
+                Solid(false),
+                Velocity::default(),
+                MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(Triangle2d::new(
+                        Vec2::new(-half.x, -half.y),
+                        Vec2::new(half.x, -half.y),
+                        Vec2::new(tall_x, half.y),
+                    ))),
+                    material: hill_material.clone(),
+                    transform: Transform::from_xyz(x, floor + half.y, 0.),
+                    ..default()
+                },
+            ))
+            .add_rollback();
+    }
 
     commands
         .spawn((
             Platform,
-            Collider::new(Vec2::new(TOP_PLATFORM_LENGTH, TOP_PLATFORM_HEIGHT)),
+            Collider::circle(MUSHROOM_RADIUS).with_layers(LAYER_WORLD, WORLD_MASK),
             Solid(false),
+            PhysicsMaterial::BOUNCY,
             Velocity::default(),
-            TransformBundle::from_transform(Transform::from_xyz(
-                TOP_PLATFORM_POS_X,
-                TOP_PLATFORM_POS_Y,
-                0.,
-            )),
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Circle::new(MUSHROOM_RADIUS))),
+                material: materials.add(MUSHROOM_COLOR),
+                transform: Transform::from_xyz(MUSHROOM_POS_X, floor + MUSHROOM_RADIUS, 0.),
+                ..default()
+            },
         ))
         .add_rollback();
 }
 
-fn spawn_vines(mut commands: Commands) {
-    commands
-        .spawn((
-            Vine,
-            Collider::new(Vec2::new(VINE_WIDTH, VINE_HEIGHT)),
-            Velocity::default(),
-            TransformBundle::from_transform(Transform::from_xyz(VINE_POS_X, VINE_POS_Y, -0.5)),
-        ))
-        .add_rollback();
+pub(crate) fn spawn_moving_platforms(mut commands: Commands) {
+    let paths = [
+        PlatformPath::Waypoints {
+            points: FERRY_POINTS.to_vec(),
+            speed: FERRY_SPEED,
+            looping: false,
+        },
+        PlatformPath::Oscillate {
+            origin: LIFT_ORIGIN,
+            extent: LIFT_EXTENT,
+            period: LIFT_PERIOD,
+        },
+    ];
+
+    for path in paths {
+        let platform = MovingPlatform::new(path);
+        let position = platform.position();
+
+        commands
+            .spawn((
+                Platform,
+                platform,
+                Collider::new(MOVING_PLATFORM_SIZE).with_layers(LAYER_WORLD, WORLD_MASK),
+                Solid(false),
+                Velocity::default(),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: MOVING_PLATFORM_COLOR,
+                        custom_size: Some(MOVING_PLATFORM_SIZE),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.)),
+                    ..default()
+                },
+            ))
+            .add_rollback();
+    }
 }
 
-fn spawn_background(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub(crate) fn spawn_vines(mut commands: Commands) {
+    for rect in &LOBBY_RECTS {
+        if rect.tile() != Some(Tile::Vine) {
+            continue;
+        }
+
+        commands
+            .spawn((
+                Vine,
+                Collider::trigger(rect.size).with_layers(LAYER_VINE, LAYER_PLAYER),
+                Velocity::default(),
+                TransformBundle::from_transform(Transform::from_translation(
+                    rect.position.extend(-0.5),
+                )),
+            ))
+            .add_rollback();
+    }
+}
+
+pub(crate) fn spawn_doors(mut commands: Commands) {
+    for rect in &LOBBY_RECTS {
+        if rect.tile() != Some(Tile::Door) {
+            continue;
+        }
+
+        commands
+            .spawn((
+                Door::default(),
+                Collider::trigger(rect.size),
+                TransformBundle::from_transform(Transform::from_translation(
+                    rect.position.extend(-0.5),
+                )),
+            ))
+            .add_rollback();
+    }
+}
+
+/// the background covers the whole level, so the camera is kept to it
+pub(crate) fn spawn_background(mut commands: Commands, asset_server: Res<AssetServer>) {
     let background_texture = asset_server.load("lobby_background.png");
 
+    commands.insert_resource(LevelBounds::centered(Vec2::ZERO, LOBBY_SIZE));
+
     commands
         .spawn(SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(10., 10.)),
+                custom_size: Some(LOBBY_SIZE),
                 ..default()
             },
             texture: background_texture,
@@ -0,0 +1,146 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "nat_game";
+
+/// where settings like key bindings live, `None` on platforms without one (the web build)
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
+
+/// where things the game records for itself live, like the match history
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(name))
+}
+
+/// reads a ron file, logging and returning `None` if it's missing or can't be parsed so callers
+/// can fall back to defaults
+pub fn load_ron<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Failed to read {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    match ron::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Failed to parse {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// writes a ron file through a temporary file and a rename, so a crash mid-write never leaves a
+/// half written file behind
+pub fn save_ron<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    write_atomic(path, contents.as_bytes())
+}
+
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp = path.with_extension("tmp");
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    fs::rename(&tmp, path)
+}
+
+/// adds one line to the end of a file, creating it if needed
+pub fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Bindings {
+        name: String,
+        keys: Vec<u32>,
+    }
+
+    /// a fresh directory for one test, so tests running at once don't share files
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{APP_DIR}_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn ron_files_round_trip() {
+        let dir = test_dir("ron");
+        let path = dir.join("nested").join("bindings.ron");
+        let bindings = Bindings {
+            name: "nat".into(),
+            keys: vec![1, 2],
+        };
+
+        save_ron(&path, &bindings).unwrap();
+
+        assert_eq!(load_ron::<Bindings>(&path), Some(bindings));
+        // nothing is left behind from the write
+        assert!(!path.with_extension("tmp").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_or_broken_ron_files_load_as_none() {
+        let dir = test_dir("broken");
+        let path = dir.join("bindings.ron");
+
+        assert_eq!(load_ron::<Bindings>(&path), None);
+
+        write_atomic(&path, b"(name: ").unwrap();
+        assert_eq!(load_ron::<Bindings>(&path), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn appended_lines_are_kept_in_order() {
+        let dir = test_dir("append");
+        let path = dir.join("history.jsonl");
+
+        append_line(&path, "first").unwrap();
+        append_line(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}